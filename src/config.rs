@@ -0,0 +1,451 @@
+use std::{collections::HashSet, net::SocketAddr, path::Path, time::Duration};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{
+    conn::listener::{Endpoint, Transport},
+    datamodel::{Occasion, RoomId, WorldId},
+};
+
+/* NOTE:
+ * Everything that used to be a literal somewhere in the code base.
+ * Loaded once at startup and handed to every system, never mutated.
+ */
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub persistence: PersistenceConfig,
+    // absent if this deployment does not run a login server at all
+    pub login: Option<LoginConfig>,
+    #[serde(default)]
+    pub worlds: Vec<WorldConfig>,
+    #[serde(default)]
+    pub gameplay: GameplayConfig,
+    #[serde(default)]
+    pub policy: PolicyConfig,
+    #[serde(default)]
+    pub data: DataConfig,
+    #[serde(default)]
+    pub chat: ChatConfig,
+    #[serde(default)]
+    pub moderation: ModerationConfig,
+    #[serde(default)]
+    pub mail: MailConfig,
+}
+
+/// Static game data, read once at startup
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct DataConfig {
+    pub rooms: String,
+    pub items: String,
+    pub furniture: String,
+    pub igloos: String,
+    pub floors: String,
+    pub locations: String,
+    pub stamps: String,
+    pub puffles: String,
+}
+
+impl Default for DataConfig {
+    fn default() -> Self {
+        Self {
+            rooms: "data/rooms.json".to_owned(),
+            items: "data/items.json".to_owned(),
+            furniture: "data/furniture.json".to_owned(),
+            igloos: "data/igloos.json".to_owned(),
+            floors: "data/floors.json".to_owned(),
+            locations: "data/locations.json".to_owned(),
+            stamps: "data/stamps.json".to_owned(),
+            puffles: "data/puffles.json".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PersistenceConfig {
+    // optional json seed, imported on every start without overwriting anything
+    pub seed: Option<String>,
+    // sqlite file, or ":memory:" for a volatile store
+    pub database: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoginConfig {
+    pub address: SocketAddr,
+    // additionally accept browser clients here
+    pub websocket: Option<SocketAddr>,
+    #[serde(default = "default_login_key_ttl_secs")]
+    pub login_key_ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorldConfig {
+    pub id: WorldId,
+    pub name: String,
+    pub address: SocketAddr,
+    pub websocket: Option<SocketAddr>,
+    pub capacity: usize,
+    #[serde(default)]
+    pub protocol: ClientProtocol,
+}
+
+/// Which client generation a world talks to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientProtocol {
+    /// Legacy client
+    #[default]
+    As2,
+    /// Vanilla client
+    As3,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct GameplayConfig {
+    // a random one is picked whenever a penguin joins the world
+    pub spawn_rooms: Vec<RoomId>,
+    // UTC-N, positive N. For America/Vancouver PDT, it's N=7
+    pub server_time_offset: usize,
+    pub egg_timer_minutes: usize,
+    pub revision: String,
+    pub max_buddies: usize,
+    pub membership: MembershipConfig,
+}
+
+/// Handed to every penguin until memberships are actually tracked
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct MembershipConfig {
+    pub member: bool,
+    pub membership_days: u32,
+    pub membership_days_remain: usize,
+}
+
+/* NOTE:
+ * Words are matched whole and case insensitive, the harshest tier wins.
+ * tolerate: masked with '*' and sent anyway
+ * consider: the message is dropped and counts as an offense
+ * ban: the message is dropped and the player banned right away
+ */
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ChatConfig {
+    pub tolerate: Vec<String>,
+    pub consider: Vec<String>,
+    pub ban: Vec<String>,
+    // offenses until the player is banned automatically, 0 never bans
+    pub max_offenses: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ModerationConfig {
+    // how long `o#b` and `!ban` keep a penguin out unless told otherwise
+    pub ban_hours: u64,
+    // same for the chat filter running out of patience
+    pub autoban_hours: u64,
+}
+
+impl Default for ModerationConfig {
+    fn default() -> Self {
+        Self {
+            ban_hours: 24,
+            autoban_hours: 24,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct MailConfig {
+    // coins taken from the sender of every postcard
+    pub postcard_cost: usize,
+    // postcards a mailbox holds until the owner deletes some
+    pub mailbox_size: usize,
+    // sent by "sys", none unless configured
+    pub system: Vec<SystemPostcard>,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            postcard_cost: 10,
+            mailbox_size: 100,
+            system: Vec::new(),
+        }
+    }
+}
+
+/* NOTE:
+ * A rule for postcards the server sends by itself.
+ * `details` is a template, "{nickname}" and "{details}" are replaced
+ * with the recipient's nickname and whatever came along with the occasion
+ * (the party's name, the moderator's reason).
+ */
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SystemPostcard {
+    pub on: Occasion,
+    // from the client's postcard crumbs
+    pub postcard_type: usize,
+    #[serde(default = "default_system_postcard_details")]
+    pub details: String,
+}
+
+fn default_system_postcard_details() -> String {
+    "{details}".to_owned()
+}
+
+/// Flash socket policy, served to clients asking with `<policy-file-request/>`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct PolicyConfig {
+    pub domains: Vec<String>,
+    // empty: every port this deployment has configured
+    pub ports: Vec<u16>,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            domains: vec!["*".to_owned()],
+            ports: Vec::new(),
+        }
+    }
+}
+
+fn default_login_key_ttl_secs() -> u64 {
+    120
+}
+
+impl Default for GameplayConfig {
+    fn default() -> Self {
+        Self {
+            spawn_rooms: vec![230],
+            server_time_offset: 7,
+            egg_timer_minutes: 24 * 60,
+            revision: "houdini".to_owned(),
+            max_buddies: 100,
+            membership: MembershipConfig::default(),
+        }
+    }
+}
+
+impl Default for MembershipConfig {
+    fn default() -> Self {
+        Self {
+            member: true,
+            membership_days: 9,
+            membership_days_remain: 1000,
+        }
+    }
+}
+
+impl LoginConfig {
+    pub fn login_key_ttl(&self) -> Duration {
+        Duration::from_secs(self.login_key_ttl_secs)
+    }
+
+    pub fn endpoints(&self) -> Vec<Endpoint> {
+        endpoints(self.address, self.websocket)
+    }
+}
+
+impl WorldConfig {
+    pub fn endpoints(&self) -> Vec<Endpoint> {
+        endpoints(self.address, self.websocket)
+    }
+}
+
+fn endpoints(address: SocketAddr, websocket: Option<SocketAddr>) -> Vec<Endpoint> {
+    let tcp = Endpoint {
+        address,
+        transport: Transport::Tcp,
+    };
+    let ws = websocket.map(|address| Endpoint {
+        address,
+        transport: Transport::WebSocket,
+    });
+    std::iter::once(tcp).chain(ws).collect()
+}
+
+impl Config {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+        Self::parse(&raw).with_context(|| format!("bad config file {}", path.display()))
+    }
+
+    pub fn parse(raw: &str) -> Result<Self> {
+        let config: Config = toml::from_str(raw)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn world(&self, id: WorldId) -> Option<&WorldConfig> {
+        self.worlds.iter().find(|w| w.id == id)
+    }
+
+    /// Policy with the ports filled in, if left to the login and world addresses
+    pub fn policy(&self) -> PolicyConfig {
+        let mut policy = self.policy.clone();
+        if policy.ports.is_empty() {
+            policy.ports = self
+                .login
+                .iter()
+                .map(|login| login.address.port())
+                .chain(self.worlds.iter().map(|world| world.address.port()))
+                .collect();
+        }
+        policy
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.login.is_none() && self.worlds.is_empty() {
+            anyhow::bail!("neither a [login] server nor any [[worlds]] are configured");
+        }
+
+        let mut ids = HashSet::new();
+        let mut addresses = HashSet::new();
+        let mut claim = |endpoints: Vec<Endpoint>| -> Result<()> {
+            for endpoint in endpoints {
+                if !addresses.insert(endpoint.address) {
+                    anyhow::bail!("address {} is configured twice", endpoint.address);
+                }
+            }
+            Ok(())
+        };
+        if let Some(login) = &self.login {
+            claim(login.endpoints())?;
+            if login.login_key_ttl_secs == 0 {
+                anyhow::bail!("login.login_key_ttl_secs must be positive");
+            }
+        }
+        for world in &self.worlds {
+            if !ids.insert(world.id) {
+                anyhow::bail!("world id {} is configured twice", world.id);
+            }
+            claim(world.endpoints()).with_context(|| format!("world {}", world.id))?;
+            if world.name.trim().is_empty() {
+                anyhow::bail!("world {}: name must not be empty", world.id);
+            }
+            if world.capacity == 0 {
+                anyhow::bail!("world {}: capacity must be positive", world.id);
+            }
+        }
+
+        if self.gameplay.spawn_rooms.is_empty() {
+            anyhow::bail!("gameplay.spawn_rooms must list at least one room");
+        }
+        if self.gameplay.server_time_offset > 24 {
+            anyhow::bail!(
+                "gameplay.server_time_offset is in hours, {} is out of range",
+                self.gameplay.server_time_offset
+            );
+        }
+        if self.moderation.ban_hours == 0 || self.moderation.autoban_hours == 0 {
+            anyhow::bail!("moderation ban hours must be positive");
+        }
+        if self.mail.mailbox_size == 0 {
+            anyhow::bail!("mail.mailbox_size must be positive");
+        }
+        if self.policy.domains.is_empty() {
+            anyhow::bail!("policy.domains must list at least one domain, \"*\" allows all");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMAL: &str = r#"
+        [persistence]
+        database = ":memory:"
+
+        [login]
+        address = "127.0.0.1:6969"
+
+        [[worlds]]
+        id = 3100
+        name = "Blizzard"
+        address = "127.0.0.1:1337"
+        capacity = 300
+    "#;
+
+    #[test]
+    fn minimal_with_defaults() {
+        let config = Config::parse(MINIMAL).expect("failed to parse");
+        assert_eq!(config.world(3100).unwrap().name, "Blizzard");
+        assert_eq!(config.gameplay.spawn_rooms, vec![230]);
+        assert_eq!(config.world(3100).unwrap().protocol, ClientProtocol::As2);
+        assert_eq!(config.policy().ports, vec![6969, 1337]);
+        assert_eq!(
+            config.login.unwrap().login_key_ttl(),
+            Duration::from_secs(120)
+        );
+    }
+
+    #[test]
+    fn system_postcards() {
+        let raw = format!(
+            "{MINIMAL}
+            [[mail.system]]
+            on = \"warned\"
+            postcard_type = 7"
+        );
+        let config = Config::parse(&raw).expect("failed to parse");
+        assert_eq!(config.mail.system[0].on, Occasion::Warned);
+        assert_eq!(config.mail.system[0].details, "{details}");
+
+        let unknown = raw.replace("warned", "birthday");
+        assert!(Config::parse(&unknown).is_err());
+    }
+
+    #[test]
+    fn shipped_config_is_valid() {
+        Config::parse(include_str!("../config.toml")).expect("config.toml is broken");
+    }
+
+    #[test]
+    fn rejects_bad_configs() {
+        let duplicate = format!(
+            "{MINIMAL}
+            [[worlds]]
+            id = 3100
+            name = \"Twin\"
+            address = \"127.0.0.1:1338\"
+            capacity = 300"
+        );
+        assert!(Config::parse(&duplicate).is_err());
+
+        let same_port = MINIMAL.replace(
+            "capacity = 300",
+            "websocket = \"127.0.0.1:6969\"\ncapacity = 300",
+        );
+        assert!(Config::parse(&same_port).is_err());
+
+        let empty_spawn = format!("{MINIMAL}\n[gameplay]\nspawn_rooms = []");
+        assert!(Config::parse(&empty_spawn).is_err());
+
+        let typo = format!("{MINIMAL}\n[gameplay]\nspawn_room = [100]");
+        assert!(Config::parse(&typo).is_err());
+
+        let no_domains = format!("{MINIMAL}\n[policy]\ndomains = []");
+        assert!(Config::parse(&no_domains).is_err());
+
+        let forever = format!("{MINIMAL}\n[moderation]\nban_hours = 0\nautoban_hours = 24");
+        assert!(Config::parse(&forever).is_err());
+
+        let nothing = "[persistence]\ndatabase = \":memory:\"";
+        assert!(Config::parse(nothing).is_err());
+    }
+}
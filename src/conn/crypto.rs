@@ -0,0 +1,113 @@
+use rand::{distr::Alphanumeric, Rng};
+
+/// Appended by the client before hashing, identical across all legacy clients
+pub const LOGIN_SALT: &str = "Y(02.>'H}t\":E1";
+
+const RANDOM_KEY_LEN: usize = 12;
+
+pub fn md5_hex(data: &str) -> String {
+    format!("{:x}", md5::compute(data.as_bytes()))
+}
+
+/// Swap both halves of an md5 hex digest, the client does this all over the place
+pub fn swap(hash: &str) -> String {
+    let (head, tail) = hash.split_at(hash.len() / 2);
+    format!("{tail}{head}")
+}
+
+/* NOTE:
+ * The client never sends the password itself, instead it sends
+ * swap(md5(swap(md5(password)) + random_key + LOGIN_SALT))
+ * Since we store md5(password) we can rebuild the very same hash.
+ */
+pub fn login_hash(password_hash: &str, random_key: &str) -> String {
+    swap(&md5_hex(&format!(
+        "{}{random_key}{LOGIN_SALT}",
+        swap(&password_hash.to_lowercase())
+    )))
+}
+
+pub fn verify_login_hash(password_hash: &str, random_key: &str, sent: &str) -> bool {
+    login_hash(password_hash, random_key).eq_ignore_ascii_case(sent)
+}
+
+/* NOTE:
+ * World servers never see the password, the client proves it owns the
+ * login key handed out by the login server instead:
+ * swap(md5(login_key + random_key)) + login_key
+ */
+pub fn world_login_hash(login_key: &str, random_key: &str) -> String {
+    format!(
+        "{}{login_key}",
+        swap(&md5_hex(&format!("{login_key}{random_key}")))
+    )
+}
+
+/// Split what the client sent to a world server into the login key, if well-formed
+pub fn verify_world_login_hash<'a>(random_key: &str, sent: &'a str) -> Option<&'a str> {
+    let login_key = sent.get(32..).filter(|key| !key.is_empty())?;
+    world_login_hash(login_key, random_key)
+        .eq_ignore_ascii_case(sent)
+        .then_some(login_key)
+}
+
+/// Same shape as an md5 digest, which is what clients expect
+pub fn login_key() -> String {
+    md5_hex(&random_key())
+}
+
+/// Fresh key for every connection, otherwise login hashes could be replayed
+pub fn random_key() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(RANDOM_KEY_LEN)
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_halves() {
+        assert_eq!(swap("aabb"), "bbaa");
+        assert_eq!(
+            swap("5f4dcc3b5aa765d61d8327deb882cf99"),
+            "1d8327deb882cf995f4dcc3b5aa765d6"
+        );
+    }
+
+    #[test]
+    fn login_hash_roundtrip() {
+        let password_hash = md5_hex("password");
+        // what a client sends for the password "password" and the key "houdini"
+        let sent = "bd1459cf13607589d68f37527d6c942f";
+        assert!(verify_login_hash(&password_hash, "houdini", sent));
+        assert!(verify_login_hash(
+            &password_hash,
+            "houdini",
+            &sent.to_uppercase()
+        ));
+        assert!(!verify_login_hash(&password_hash, "other", sent));
+        assert!(!verify_login_hash(&md5_hex("hunter2"), "houdini", sent));
+    }
+
+    #[test]
+    fn world_login_hash_roundtrip() {
+        let sent = world_login_hash("fc15ebff4bae96e53d1a55ba559eca3f", "houdini");
+        assert_eq!(
+            verify_world_login_hash("houdini", &sent),
+            Some("fc15ebff4bae96e53d1a55ba559eca3f")
+        );
+        assert_eq!(verify_world_login_hash("other", &sent), None);
+        assert_eq!(verify_world_login_hash("houdini", "short"), None);
+    }
+
+    #[test]
+    fn random_keys_differ() {
+        let key = random_key();
+        assert_eq!(key.len(), RANDOM_KEY_LEN);
+        assert_ne!(key, random_key());
+    }
+}
@@ -1,17 +1,36 @@
 use std::collections::VecDeque;
 
 use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
 use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
-use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::net::TcpStream;
 
 // TODO: might be problematic for overengineered json packages later on!
 // const MAX_TCP_PACKET_SIZE: usize = 65536;
 
-pub struct LineConnWriter(WriteHalf<TcpStream>);
+/* NOTE:
+ * Lines are null-terminated no matter what carries them,
+ * a transport only moves raw bytes back and forth.
+ * Chunks don't have to line up with lines, the reader stitches them together.
+ */
+#[async_trait]
+pub trait TransportReader: Send + Sync {
+    /// Next chunk of bytes, empty once the peer is gone
+    async fn recv(&mut self) -> Result<Vec<u8>>;
+}
+
+#[async_trait]
+pub trait TransportWriter: Send + Sync {
+    async fn send(&mut self, data: &[u8]) -> Result<()>;
+}
+
+pub struct LineConnWriter(Box<dyn TransportWriter>);
 
 pub struct LineConnReader {
     queue: VecDeque<String>,
-    reader: ReadHalf<TcpStream>,
+    // bytes received past the last null terminator
+    pending: Vec<u8>,
+    reader: Box<dyn TransportReader>,
 }
 
 #[derive(Debug)]
@@ -20,17 +39,44 @@ pub enum ReadError {
     EnvError(anyhow::Error),
 }
 
-pub async fn line_con(stream: TcpStream) -> (LineConnWriter, LineConnReader) {
-    let (reader, writer) = tokio::io::split(stream);
+pub fn from_transport<W, R>(writer: W, reader: R) -> (LineConnWriter, LineConnReader)
+where
+    W: TransportWriter + 'static,
+    R: TransportReader + 'static,
+{
     (
-        LineConnWriter(writer),
+        LineConnWriter(Box::new(writer)),
         LineConnReader {
-            reader,
             queue: VecDeque::with_capacity(16),
+            pending: Vec::new(),
+            reader: Box::new(reader),
         },
     )
 }
 
+pub async fn line_con(stream: TcpStream) -> (LineConnWriter, LineConnReader) {
+    let (reader, writer) = tokio::io::split(stream);
+    from_transport(writer, reader)
+}
+
+#[async_trait]
+impl TransportReader for ReadHalf<TcpStream> {
+    async fn recv(&mut self) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; 4096];
+        let n = self.read(&mut buf).await?;
+        buf.truncate(n);
+        Ok(buf)
+    }
+}
+
+#[async_trait]
+impl TransportWriter for WriteHalf<TcpStream> {
+    async fn send(&mut self, data: &[u8]) -> Result<()> {
+        self.write_all(data).await?;
+        Ok(())
+    }
+}
+
 //TODO: eh this convenience function is stupid!!
 // we realistically should only pass in the line
 // caller should take care of serialization
@@ -39,10 +85,10 @@ impl LineConnWriter {
     where
         T: Into<String>,
     {
-        let mut line: String = data.try_into().context("failed to convert into line")?;
+        let mut line: String = data.into();
         line.push('\0');
         self.0
-            .write_all(line.as_bytes())
+            .send(line.as_bytes())
             .await
             .context("failed to write line")?;
         Ok(())
@@ -60,7 +106,7 @@ impl LineConnReader {
             .read_string()
             .await
             .context("failed to read line")
-            .map_err(|e| ReadError::EnvError(e))?
+            .map_err(ReadError::EnvError)?
         {
             Some(line) => line,
             None => return Ok(None),
@@ -74,57 +120,73 @@ impl LineConnReader {
     }
 
     async fn read_string(&mut self) -> Result<Option<String>> {
-        if let Some(line) = self.queue.pop_front() {
-            return Ok(Some(line));
-        }
-
-        let mut accumulated = Vec::new();
-        let mut buf = [0u8; 4096];
-
         loop {
-            let n = self.reader.read(&mut buf).await?;
+            if let Some(line) = self.queue.pop_front() {
+                return Ok(Some(line));
+            }
 
-            if n == 0 {
-                return if accumulated.is_empty() {
+            let chunk = self.reader.recv().await?;
+            if chunk.is_empty() {
+                return if self.pending.is_empty() {
                     Ok(None)
                 } else {
                     Err(anyhow!("Connection closed before null terminator"))
                 };
             }
 
-            let mut start = 0;
-            let mut first_result = None;
-
-            for i in 0..n {
-                if buf[i] == 0 {
-                    if i + 1 < n && buf[i + 1] == 0 {
-                        break;
-                    }
-
-                    let mut line_bytes = accumulated.clone();
-                    line_bytes.extend_from_slice(&buf[start..i]);
-
-                    let s = String::from_utf8(line_bytes).context("failed to decode as utf-8")?;
-
-                    if first_result.is_none() {
-                        first_result = Some(s);
-                    } else {
-                        self.queue.push_back(s);
-                    }
-
-                    accumulated.clear();
-                    start = i + 1;
+            self.pending.extend_from_slice(&chunk);
+            while let Some(end) = self.pending.iter().position(|b| *b == 0) {
+                let mut line: Vec<u8> = self.pending.drain(..=end).collect();
+                line.pop();
+                // consecutive terminators carry nothing
+                if line.is_empty() {
+                    continue;
                 }
+                let line = String::from_utf8(line).context("failed to decode as utf-8")?;
+                self.queue.push_back(line);
             }
+        }
+    }
+}
 
-            if let Some(result) = first_result {
-                if start < n {
-                    accumulated.extend_from_slice(&buf[start..n]);
-                }
-                return Ok(Some(result));
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Chunks(VecDeque<&'static [u8]>);
 
-            accumulated.extend_from_slice(&buf[..n]);
+    #[async_trait]
+    impl TransportReader for Chunks {
+        async fn recv(&mut self) -> Result<Vec<u8>> {
+            Ok(self.0.pop_front().unwrap_or_default().to_vec())
         }
     }
+
+    #[tokio::test]
+    async fn lines_across_chunks() {
+        let chunks = Chunks(VecDeque::from([
+            b"<policy-file-request/>\0%xt%s%".as_slice(),
+            b"j#js%-1%\0\0%xt%s%u#h".as_slice(),
+            b"%-1%\0".as_slice(),
+        ]));
+        let mut reader = LineConnReader {
+            queue: VecDeque::new(),
+            pending: Vec::new(),
+            reader: Box::new(chunks),
+        };
+
+        assert_eq!(
+            reader.read_string().await.unwrap().as_deref(),
+            Some("<policy-file-request/>")
+        );
+        assert_eq!(
+            reader.read_string().await.unwrap().as_deref(),
+            Some("%xt%s%j#js%-1%")
+        );
+        assert_eq!(
+            reader.read_string().await.unwrap().as_deref(),
+            Some("%xt%s%u#h%-1%")
+        );
+        assert_eq!(reader.read_string().await.unwrap(), None);
+    }
 }
@@ -0,0 +1,79 @@
+use std::net::SocketAddr;
+
+use anyhow::{Context, Result};
+use futures_util::future::select_all;
+use serde::Deserialize;
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::conn::{
+    line::{self, LineConnReader, LineConnWriter},
+    websocket,
+};
+
+/// What carries the null-terminated lines of a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// Plain socket, flash projectors
+    Tcp,
+    /// Browsers running Ruffle
+    WebSocket,
+}
+
+impl Transport {
+    pub async fn establish(self, stream: TcpStream) -> Result<(LineConnWriter, LineConnReader)> {
+        match self {
+            Transport::Tcp => Ok(line::line_con(stream).await),
+            Transport::WebSocket => websocket::websocket_con(stream).await,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Endpoint {
+    pub address: SocketAddr,
+    pub transport: Transport,
+}
+
+pub struct Listener {
+    listener: TcpListener,
+    transport: Transport,
+}
+
+impl Listener {
+    pub async fn bind(endpoint: Endpoint) -> Result<Self> {
+        let listener = TcpListener::bind(endpoint.address)
+            .await
+            .with_context(|| format!("failed to bind to {}", endpoint.address))?;
+        log::info!(
+            "listening for {:?} connections on {}",
+            endpoint.transport,
+            listener.local_addr()?
+        );
+        Ok(Self {
+            listener,
+            transport: endpoint.transport,
+        })
+    }
+
+    pub async fn bind_all(endpoints: &[Endpoint]) -> Result<Vec<Self>> {
+        let mut listeners = Vec::with_capacity(endpoints.len());
+        for endpoint in endpoints {
+            listeners.push(Self::bind(*endpoint).await?);
+        }
+        Ok(listeners)
+    }
+}
+
+/// Next connection on any of the listeners, not yet established
+pub async fn accept_any(
+    listeners: &[Listener],
+) -> std::io::Result<(TcpStream, SocketAddr, Transport)> {
+    let accepts = listeners.iter().map(|l| {
+        Box::pin(async move {
+            let (stream, addr) = l.listener.accept().await?;
+            Ok((stream, addr, l.transport))
+        })
+    });
+    select_all(accepts).await.0
+}
@@ -1,39 +1,130 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 
-use crate::pkt;
+use crate::{
+    config::PolicyConfig,
+    conn::{crypto, login_key::KeyStore},
+    persistence,
+    pkt::{self, meta},
+};
 
+/// What the client has to prove in the `pword` field
+#[derive(Clone)]
+pub enum Verification {
+    /// Login server: the password itself (hashed)
+    Password,
+    /// World server: a login key handed out by the login server
+    LoginKey(KeyStore),
+}
 
-pub struct LoginHandler {}
+/// XML handshake shared by the login server and the world servers
+pub struct LoginHandler {
+    persistence: persistence::Manager,
+    verification: Verification,
+    policy: PolicyConfig,
+    random_key: String,
+}
 
 pub enum LoginResp {
     /// User finished
-    HandShook,
+    HandShook(persistence::Account),
 
     /// Forward Data
     Packet(pkt::xml::server::Packet),
 
+    /// Credentials were refused, tell the client and close the connection
+    Rejected(meta::server::Error),
 
-    // TODO: we need some termination logic!!!
+    /// Credentials are fine but the penguin is banned, None for good
+    Banned { hours_left: Option<u64> },
 }
 
 impl LoginHandler {
-    pub async fn new() -> Result<Self> {
-        Ok(Self{})
+    pub async fn new(
+        persistence: persistence::Manager,
+        verification: Verification,
+        policy: PolicyConfig,
+    ) -> Result<Self> {
+        Ok(Self {
+            persistence,
+            verification,
+            policy,
+            random_key: crypto::random_key(),
+        })
     }
 
     pub async fn handle(&mut self, packet: &pkt::xml::client::Packet) -> Result<LoginResp> {
         match packet {
+            pkt::xml::client::Packet::PolicyFileRequest => {
+                Ok(LoginResp::Packet(pkt::xml::server::Packet::PolicyFile {
+                    domains: self.policy.domains.clone(),
+                    ports: self.policy.ports.clone(),
+                }))
+            }
             pkt::xml::client::Packet::VersionCheck { expected } => {
                 log::info!("client expects version: {}", expected);
                 Ok(LoginResp::Packet(pkt::xml::server::Packet::ApiOK))
             }
             pkt::xml::client::Packet::RandomKey => Ok(LoginResp::Packet(
-                pkt::xml::server::Packet::RandomKey("houdini".to_owned())
+                pkt::xml::server::Packet::RandomKey(self.random_key.clone()),
             )),
-            pkt::xml::client::Packet::Login { username, password: _ } => {
+            pkt::xml::client::Packet::Login { username, password } => {
                 log::info!("user: {username} is logging in!");
-                Ok(LoginResp::HandShook)
+                self.authenticate(username, password).await
+            }
+        }
+    }
+
+    async fn authenticate(&self, username: &str, login_hash: &str) -> Result<LoginResp> {
+        if username.is_empty() {
+            return Ok(LoginResp::Rejected(meta::server::Error::NameRequired));
+        }
+        if login_hash.is_empty() {
+            return Ok(LoginResp::Rejected(meta::server::Error::PasswordRequired));
+        }
+
+        let account = match self
+            .persistence
+            .load_account(username)
+            .await
+            .context("failed to load account")?
+        {
+            Some(account) => account,
+            None => return Ok(LoginResp::Rejected(meta::server::Error::NameNotFound)),
+        };
+
+        match &self.verification {
+            Verification::Password => {
+                if !crypto::verify_login_hash(&account.password_hash, &self.random_key, login_hash)
+                {
+                    log::info!("wrong password for {username}");
+                    return Ok(LoginResp::Rejected(meta::server::Error::PasswordWrong));
+                }
             }
+            Verification::LoginKey(keys) => {
+                let Some(login_key) = crypto::verify_world_login_hash(&self.random_key, login_hash)
+                else {
+                    log::info!("malformed login key hash for {username}");
+                    return Ok(LoginResp::Rejected(meta::server::Error::PasswordWrong));
+                };
+                if let Err(e) = keys.redeem(account.id, login_key).await {
+                    log::info!("login key of {username} refused: {e}");
+                    return Ok(LoginResp::Rejected(e.into()));
+                }
+            }
+        }
+
+        let now = persistence::now();
+        if let Some(ban) = self
+            .persistence
+            .active_ban(account.id, now)
+            .await
+            .context("failed to look up bans")?
+        {
+            log::info!("{username} is banned: {}", ban.reason);
+            return Ok(LoginResp::Banned {
+                hours_left: ban.hours_left(now),
+            });
         }
+        Ok(LoginResp::HandShook(account))
     }
 }
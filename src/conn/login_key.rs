@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::{
+    conn::crypto,
+    persistence::{self, LoginKey},
+    pkt::meta,
+};
+
+/// Login keys are minted by the login server and redeemed by a world server
+pub const DEFAULT_LOGIN_KEY_TTL: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum KeyError {
+    /// Never issued, or already used up
+    #[error("no login key pending for this player")]
+    Unknown,
+
+    /// Issued, but the client took too long
+    #[error("login key expired")]
+    Expired,
+
+    /// Someone else's key, or garbage
+    #[error("login key does not match")]
+    Mismatch,
+
+    /// Persistence failed, already logged
+    #[error("login keys are unavailable")]
+    Unavailable,
+}
+
+impl From<KeyError> for meta::server::Error {
+    fn from(value: KeyError) -> Self {
+        match value {
+            KeyError::Unknown | KeyError::Mismatch => meta::server::Error::PasswordWrong,
+            KeyError::Expired => meta::server::Error::TimeOut,
+            KeyError::Unavailable => meta::server::Error::NoDbConnection,
+        }
+    }
+}
+
+/* NOTE:
+ * A key goes through two hands on a world server:
+ * the authgate redeems the issued key during the xml handshake,
+ * then JoinServer has to present the very same key once more.
+ * Both steps consume, so neither can be replayed.
+ * A wrong key consumes nothing, or anyone could lock a penguin out.
+ * Keys live in persistence, the login server and the worlds
+ * only have to share a database, not a process.
+ */
+#[derive(Clone)]
+pub struct KeyStore {
+    persistence: persistence::Manager,
+    ttl: Duration,
+}
+
+impl KeyStore {
+    pub fn new(persistence: persistence::Manager, ttl: Duration) -> Self {
+        Self { persistence, ttl }
+    }
+
+    /// Mint a new key, any older key of the player is invalidated
+    pub async fn issue(&self, player_id: meta::PlayerId) -> Result<String> {
+        let now = persistence::now();
+        // nobody came back for these, don't let them pile up
+        self.persistence
+            .prune_login_keys(now.saturating_sub(self.ttl.as_secs()))
+            .await?;
+        let key = crypto::login_key();
+        self.persistence
+            .put_login_key(&LoginKey {
+                penguin_id: player_id,
+                key: key.clone(),
+                issued_at: now,
+                redeemed: false,
+            })
+            .await?;
+        Ok(key)
+    }
+
+    pub async fn redeem(&self, player_id: meta::PlayerId, key: &str) -> Result<(), KeyError> {
+        let issued = self.take(player_id, key, false).await?;
+        self.persistence
+            .put_login_key(&LoginKey {
+                redeemed: true,
+                issued_at: persistence::now(),
+                ..issued
+            })
+            .await
+            .map_err(unavailable)
+    }
+
+    /// The handshake and JoinServer must happen within the same ttl
+    pub async fn confirm(&self, player_id: meta::PlayerId, key: &str) -> Result<(), KeyError> {
+        self.take(player_id, key, true).await.map(drop)
+    }
+
+    async fn take(
+        &self,
+        player_id: meta::PlayerId,
+        key: &str,
+        redeemed: bool,
+    ) -> Result<LoginKey, KeyError> {
+        // only the right key is taken, guessing never locks the owner out
+        let Some(taken) = self
+            .persistence
+            .take_login_key(player_id, key)
+            .await
+            .map_err(unavailable)?
+        else {
+            return match self.persistence.has_login_key(player_id).await {
+                Ok(true) => Err(KeyError::Mismatch),
+                Ok(false) => Err(KeyError::Unknown),
+                Err(e) => Err(unavailable(e)),
+            };
+        };
+        if taken.redeemed != redeemed {
+            return Err(KeyError::Unknown);
+        }
+        if persistence::now() > taken.issued_at + self.ttl.as_secs() {
+            return Err(KeyError::Expired);
+        }
+        Ok(taken)
+    }
+}
+
+fn unavailable(e: anyhow::Error) -> KeyError {
+    log::error!("login key store failed: {e:#}");
+    KeyError::Unavailable
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::persistence::manager::{mem::MemoryManager, seed::Seed};
+
+    fn store(ttl: Duration) -> KeyStore {
+        let seed = Seed::parse(
+            r#"{"penguins": [
+                {"id": 102, "username": "kirill", "password_hash": "", "nickname": "Kirill"},
+                {"id": 103, "username": "basil", "password_hash": "", "nickname": "Basil"}
+            ]}"#,
+        )
+        .unwrap();
+        KeyStore::new(Arc::new(MemoryManager::from_seed(seed).unwrap()), ttl)
+    }
+
+    #[tokio::test]
+    async fn single_use() {
+        let store = store(DEFAULT_LOGIN_KEY_TTL);
+        let key = store.issue(102).await.unwrap();
+        // a wrong guess leaves the key to its owner
+        assert_eq!(store.redeem(102, "nope").await, Err(KeyError::Mismatch));
+        assert_eq!(store.redeem(102, &key).await, Ok(()));
+        assert_eq!(store.confirm(102, "nope").await, Err(KeyError::Mismatch));
+        assert_eq!(store.confirm(102, &key).await, Ok(()));
+        assert_eq!(store.confirm(102, &key).await, Err(KeyError::Unknown));
+        assert_eq!(store.redeem(102, "nope").await, Err(KeyError::Unknown));
+
+        // the right key out of order is burnt
+        let key = store.issue(102).await.unwrap();
+        assert_eq!(store.confirm(102, &key).await, Err(KeyError::Unknown));
+        assert_eq!(store.redeem(102, &key).await, Err(KeyError::Unknown));
+        let key = store.issue(102).await.unwrap();
+        store.redeem(102, &key).await.unwrap();
+        assert_eq!(store.redeem(102, &key).await, Err(KeyError::Unknown));
+        assert_eq!(store.confirm(102, &key).await, Err(KeyError::Unknown));
+    }
+
+    #[tokio::test]
+    async fn expiry() {
+        let store = store(Duration::from_secs(60));
+        let stale = |redeemed| LoginKey {
+            penguin_id: 102,
+            key: "stale".to_owned(),
+            issued_at: persistence::now() - 61,
+            redeemed,
+        };
+        store
+            .persistence
+            .put_login_key(&stale(false))
+            .await
+            .unwrap();
+        assert_eq!(store.redeem(102, "stale").await, Err(KeyError::Expired));
+        store.persistence.put_login_key(&stale(true)).await.unwrap();
+        assert_eq!(store.confirm(102, "stale").await, Err(KeyError::Expired));
+
+        // abandoned keys are dropped by the next issue
+        store.persistence.put_login_key(&stale(true)).await.unwrap();
+        store.issue(103).await.unwrap();
+        assert!(!store.persistence.has_login_key(102).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn keys_are_per_player() {
+        let store = store(DEFAULT_LOGIN_KEY_TTL);
+        let key = store.issue(102).await.unwrap();
+        assert_eq!(store.redeem(103, &key).await, Err(KeyError::Unknown));
+        assert_eq!(store.redeem(102, &key).await, Ok(()));
+        assert_eq!(store.confirm(102, "other").await, Err(KeyError::Mismatch));
+    }
+}
@@ -1,3 +1,7 @@
+pub mod crypto;
 pub mod line;
+pub mod listener;
 pub mod login;
+pub mod login_key;
 pub mod server_list;
+pub mod websocket;
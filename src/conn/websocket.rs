@@ -0,0 +1,61 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures_util::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    tungstenite::{error::ProtocolError, Error, Message},
+    WebSocketStream,
+};
+
+use crate::conn::line::{self, LineConnReader, LineConnWriter, TransportReader, TransportWriter};
+
+/* NOTE:
+ * Ruffle tunnels flash sockets through a websocket proxy,
+ * every message carries a raw slice of the very same null-terminated stream.
+ */
+pub async fn websocket_con(stream: TcpStream) -> Result<(LineConnWriter, LineConnReader)> {
+    let ws = tokio_tungstenite::accept_async(stream)
+        .await
+        .context("websocket handshake failed")?;
+    let (sink, stream) = ws.split();
+    Ok(line::from_transport(WsWriter(sink), WsReader(stream)))
+}
+
+struct WsWriter(SplitSink<WebSocketStream<TcpStream>, Message>);
+
+struct WsReader(SplitStream<WebSocketStream<TcpStream>>);
+
+#[async_trait]
+impl TransportWriter for WsWriter {
+    async fn send(&mut self, data: &[u8]) -> Result<()> {
+        self.0
+            .send(Message::binary(data.to_vec()))
+            .await
+            .context("failed to send websocket message")
+    }
+}
+
+#[async_trait]
+impl TransportReader for WsReader {
+    async fn recv(&mut self) -> Result<Vec<u8>> {
+        loop {
+            match self.0.next().await {
+                None | Some(Ok(Message::Close(_))) => return Ok(Vec::new()),
+                Some(Ok(Message::Binary(data))) => return Ok(data.to_vec()),
+                Some(Ok(Message::Text(text))) => return Ok(text.as_bytes().to_vec()),
+                // pings are answered by tungstenite itself
+                Some(Ok(_)) => continue,
+                // browser tabs just vanish, no different from a closed tcp socket
+                Some(Err(
+                    Error::ConnectionClosed
+                    | Error::AlreadyClosed
+                    | Error::Protocol(ProtocolError::ResetWithoutClosingHandshake),
+                )) => return Ok(Vec::new()),
+                Some(Err(e)) => return Err(e).context("failed to receive websocket message"),
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use std::ops::RangeInclusive;
+
+use crate::datamodel::ItemId;
+
+/* NOTE:
+ * What the stock client sends, anything outside came from a modified one.
+ * The id ranges are a little generous, they keep things sane rather than exact.
+ */
+pub const EMOTES: RangeInclusive<usize> = 1..=30;
+// standing and walking in eight directions, sitting, waving (25) and dancing (26)
+pub const FRAMES: RangeInclusive<u8> = 1..=26;
+// one off animations, these share the frame numbering
+pub const ACTIONS: RangeInclusive<u8> = 1..=26;
+pub const JOKES: RangeInclusive<usize> = 0..=99;
+// the safe chat menu, the lines of the games and the rooms with a tour
+pub const SAFE_MESSAGES: RangeInclusive<usize> = 0..=999;
+pub const LINE_MESSAGES: RangeInclusive<usize> = 0..=999;
+pub const TOUR_MESSAGES: RangeInclusive<usize> = 0..=999;
+pub const GUIDE_MESSAGES: RangeInclusive<usize> = 0..=99;
+
+// guide messages are for penguins who passed the tour guide quiz, and moderators
+pub const TOUR_GUIDE_HAT: ItemId = 428;
+
+// every room is drawn on the same stage
+pub const STAGE_WIDTH: isize = 760;
+pub const STAGE_HEIGHT: isize = 480;
+
+pub fn on_stage(x: isize, y: isize) -> bool {
+    (0..=STAGE_WIDTH).contains(&x) && (0..=STAGE_HEIGHT).contains(&y)
+}
@@ -0,0 +1,135 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    config::DataConfig,
+    datamodel::{
+        igloo::{Floor, Furniture, Igloo, Location},
+        item::ItemCatalog,
+        puffle::Puffle,
+        room::RoomCatalog,
+        stamp::Stamp,
+    },
+};
+
+/// Everything static about the game, loaded once and shared read-only by every system
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    pub items: ItemCatalog,
+    pub rooms: RoomCatalog,
+    pub furniture: Table<Furniture>,
+    pub igloos: Table<Igloo>,
+    pub floors: Table<Floor>,
+    pub locations: Table<Location>,
+    pub stamps: Table<Stamp>,
+    pub puffles: Table<Puffle>,
+}
+
+impl Catalog {
+    pub fn load(data: &DataConfig) -> Result<Self> {
+        Ok(Self {
+            items: ItemCatalog::from_file(&data.items)?,
+            rooms: RoomCatalog::from_file(&data.rooms)?,
+            furniture: Table::from_file(&data.furniture)?,
+            igloos: Table::from_file(&data.igloos)?,
+            floors: Table::from_file(&data.floors)?,
+            locations: Table::from_file(&data.locations)?,
+            stamps: Table::from_file(&data.stamps)?,
+            puffles: Table::from_file(&data.puffles)?,
+        })
+    }
+}
+
+/// A crumbs entry that is deserialized as is, no massaging needed
+pub trait Record: DeserializeOwned {
+    // used in error messages
+    const KIND: &'static str;
+
+    fn id(&self) -> usize;
+
+    fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/* NOTE:
+ * The simple crumbs files are plain lists:
+ * [{"igloo_id": 1, "name": "Basic Igloo", "cost": 1500}]
+ * Rooms and items have their quirks and keep their own catalogs.
+ */
+#[derive(Debug, Clone)]
+pub struct Table<T>(HashMap<usize, T>);
+
+impl<T> Default for Table<T> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+impl<T: Record> Table<T> {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {} catalog {}", T::KIND, path.display()))?;
+        Self::parse(&raw).with_context(|| format!("bad {} catalog {}", T::KIND, path.display()))
+    }
+
+    pub fn parse(raw: &str) -> Result<Self> {
+        let raw: Vec<T> =
+            serde_json::from_str(raw).with_context(|| format!("failed to parse {}", T::KIND))?;
+        let mut records = HashMap::with_capacity(raw.len());
+        for record in raw {
+            let id = record.id();
+            record
+                .validate()
+                .with_context(|| format!("{} {id} is invalid", T::KIND))?;
+            if records.insert(id, record).is_some() {
+                anyhow::bail!("{} {id} is listed twice", T::KIND);
+            }
+        }
+        Ok(Self(records))
+    }
+
+    pub fn get(&self, id: usize) -> Option<&T> {
+        self.0.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.0.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_rejects_duplicates() {
+        let raw = r#"[{"igloo_id": 1, "name": "Basic Igloo", "cost": 1500},
+                      {"igloo_id": 1, "name": "Candy Igloo", "cost": 1500}]"#;
+        assert!(Table::<Igloo>::parse(raw).is_err());
+    }
+
+    #[test]
+    fn shipped_data_loads() {
+        let catalog = Catalog::load(&DataConfig::default()).expect("failed to load data/");
+        assert!(catalog.rooms.get(100).is_some());
+        assert!(catalog.items.get(1).is_some());
+        assert!(catalog.igloos.get(1).is_some());
+        assert!(catalog.floors.get(1).is_some());
+        assert!(catalog.locations.get(1).is_some());
+        assert!(!catalog.furniture.is_empty());
+        assert!(!catalog.stamps.is_empty());
+        assert!(catalog.puffles.get(0).is_some());
+    }
+}
@@ -0,0 +1,90 @@
+use serde::Deserialize;
+
+use crate::datamodel::{catalog::Record, FloorId, FurnitureId, IglooId, LocationId};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Furniture {
+    #[serde(rename = "furniture_item_id")]
+    pub id: FurnitureId,
+    #[serde(rename = "label")]
+    pub name: String,
+    #[serde(default)]
+    pub cost: usize,
+    #[serde(rename = "is_member", default)]
+    pub member: bool,
+    // how many of it fit in one inventory
+    #[serde(default = "max_furniture_quantity")]
+    pub max_quantity: usize,
+}
+
+fn max_furniture_quantity() -> usize {
+    99
+}
+
+impl Record for Furniture {
+    const KIND: &'static str = "furniture";
+
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.max_quantity == 0 {
+            anyhow::bail!("nobody may own any");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Igloo {
+    #[serde(rename = "igloo_id")]
+    pub id: IglooId,
+    pub name: String,
+    #[serde(default)]
+    pub cost: usize,
+}
+
+impl Record for Igloo {
+    const KIND: &'static str = "igloo";
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Floor {
+    #[serde(rename = "igloo_floor_id")]
+    pub id: FloorId,
+    #[serde(rename = "label")]
+    pub name: String,
+    #[serde(default)]
+    pub cost: usize,
+}
+
+impl Record for Floor {
+    const KIND: &'static str = "floor";
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Location {
+    #[serde(rename = "igloo_location_id")]
+    pub id: LocationId,
+    #[serde(rename = "label")]
+    pub name: String,
+    #[serde(default)]
+    pub cost: usize,
+}
+
+impl Record for Location {
+    const KIND: &'static str = "location";
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
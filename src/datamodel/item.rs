@@ -0,0 +1,219 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::datamodel::ItemId;
+
+/// Where an item goes on a penguin, numbered like the `type` in the crumbs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Slot {
+    Color = 1,
+    Head = 2,
+    Face = 3,
+    Neck = 4,
+    Body = 5,
+    Hand = 6,
+    Feet = 7,
+    Flag = 8,
+    Photo = 9,
+}
+
+impl Slot {
+    pub const ALL: [Slot; 9] = [
+        Slot::Color,
+        Slot::Head,
+        Slot::Face,
+        Slot::Neck,
+        Slot::Body,
+        Slot::Hand,
+        Slot::Feet,
+        Slot::Flag,
+        Slot::Photo,
+    ];
+
+    /// A penguin can go without anything but a color
+    pub fn may_be_empty(self) -> bool {
+        self != Slot::Color
+    }
+}
+
+/// What an item is, numbered like the `type` in the crumbs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ItemType {
+    Color = 1,
+    Head = 2,
+    Face = 3,
+    Neck = 4,
+    Body = 5,
+    Hand = 6,
+    Feet = 7,
+    Flag = 8,
+    Photo = 9,
+    // pins and awards shown on the playercard only, never worn
+    Award = 10,
+}
+
+impl ItemType {
+    pub fn slot(self) -> Option<Slot> {
+        match self {
+            ItemType::Color => Some(Slot::Color),
+            ItemType::Head => Some(Slot::Head),
+            ItemType::Face => Some(Slot::Face),
+            ItemType::Neck => Some(Slot::Neck),
+            ItemType::Body => Some(Slot::Body),
+            ItemType::Hand => Some(Slot::Hand),
+            ItemType::Feet => Some(Slot::Feet),
+            ItemType::Flag => Some(Slot::Flag),
+            ItemType::Photo => Some(Slot::Photo),
+            ItemType::Award => None,
+        }
+    }
+}
+
+impl TryFrom<u8> for ItemType {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        Ok(match value {
+            1 => ItemType::Color,
+            2 => ItemType::Head,
+            3 => ItemType::Face,
+            4 => ItemType::Neck,
+            5 => ItemType::Body,
+            6 => ItemType::Hand,
+            7 => ItemType::Feet,
+            8 => ItemType::Flag,
+            9 => ItemType::Photo,
+            10 => ItemType::Award,
+            other => anyhow::bail!("unknown item type {other}"),
+        })
+    }
+}
+
+/* NOTE:
+ * Items follow the crumbs paper_items layout, a plain list:
+ * [{"paper_item_id": 1, "type": 1, "cost": 20, "is_member": false, "label": "Blue"}]
+ * Bait items only exist to catch modified clients, nobody gets to buy them.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub id: ItemId,
+    pub name: String,
+    pub kind: ItemType,
+    pub cost: usize,
+    pub member: bool,
+    // false for bait and retired items, those can't be bought
+    pub available: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawItem {
+    paper_item_id: ItemId,
+    label: String,
+    #[serde(rename = "type")]
+    type_id: u8,
+    #[serde(default)]
+    cost: usize,
+    #[serde(default)]
+    is_member: bool,
+    #[serde(default)]
+    is_bait: bool,
+    #[serde(default = "available_by_default")]
+    available: bool,
+}
+
+fn available_by_default() -> bool {
+    true
+}
+
+impl TryFrom<RawItem> for Item {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawItem) -> Result<Self> {
+        Ok(Self {
+            id: raw.paper_item_id,
+            name: raw.label,
+            kind: raw.type_id.try_into()?,
+            cost: raw.cost,
+            member: raw.is_member,
+            available: raw.available && !raw.is_bait,
+        })
+    }
+}
+
+/// Every item there is, never changes at runtime
+#[derive(Debug, Clone, Default)]
+pub struct ItemCatalog(HashMap<ItemId, Item>);
+
+impl ItemCatalog {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read item catalog {}", path.display()))?;
+        Self::parse(&raw).with_context(|| format!("bad item catalog {}", path.display()))
+    }
+
+    pub fn parse(raw: &str) -> Result<Self> {
+        let raw: Vec<RawItem> = serde_json::from_str(raw).context("failed to parse items")?;
+        let mut items = HashMap::with_capacity(raw.len());
+        for item in raw {
+            let id = item.paper_item_id;
+            let item = Item::try_from(item).with_context(|| format!("item {id} is invalid"))?;
+            if items.insert(id, item).is_some() {
+                anyhow::bail!("item {id} is listed twice");
+            }
+        }
+        Ok(Self(items))
+    }
+
+    pub fn get(&self, item_id: ItemId) -> Option<&Item> {
+        self.0.get(&item_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_crumbs() {
+        let items = ItemCatalog::parse(
+            r#"[
+                {"paper_item_id": 1, "type": 1, "cost": 20, "is_member": false, "label": "Blue",
+                 "layer": 1500},
+                {"paper_item_id": 413, "type": 2, "cost": 450, "is_member": true,
+                 "label": "Viking Helmet"},
+                {"paper_item_id": 3000, "type": 5, "label": "Bait", "is_bait": true},
+                {"paper_item_id": 3001, "type": 5, "label": "Retired", "available": false}
+            ]"#,
+        )
+        .expect("failed to parse");
+        assert_eq!(items.len(), 4);
+        assert_eq!(items.get(1).unwrap().cost, 20);
+        assert!(items.get(413).unwrap().member);
+        assert!(items.get(1).unwrap().available);
+        assert!(!items.get(3000).unwrap().available);
+        assert!(!items.get(3001).unwrap().available);
+        assert!(items.get(2).is_none());
+        assert_eq!(items.get(413).unwrap().kind.slot(), Some(Slot::Head));
+    }
+
+    #[test]
+    fn rejects_duplicates() {
+        let raw = r#"[{"paper_item_id": 1, "type": 1, "label": "Blue"},
+                      {"paper_item_id": 1, "type": 1, "label": "Blue"}]"#;
+        assert!(ItemCatalog::parse(raw).is_err());
+
+        let unknown_type = r#"[{"paper_item_id": 1, "type": 42, "label": "Blue"}]"#;
+        assert!(ItemCatalog::parse(unknown_type).is_err());
+    }
+}
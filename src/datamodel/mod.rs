@@ -1,16 +1,68 @@
-/* TODO:
- * some item "holders" are more restrictive.
- * I can't equip a shirt as a hat
- * this will do fine for now.
- * but we need some validation at some point ...
- *
- * perhaps we could do an enum?
- * but ehhhhh
+pub mod action;
+pub mod catalog;
+pub mod igloo;
+pub mod item;
+pub mod puffle;
+pub mod room;
+pub mod stamp;
+
+use serde::Deserialize;
+
+/* NOTE:
+ * Ids stay bare, what an item may be used for is up to `item::ItemType`,
+ * looked up in the catalog.
  */
 pub type ItemId = usize;
 pub type PlayerId = usize;
 pub type RoomId = usize;
+pub type WorldId = usize;
+pub type PuffleId = usize;
+pub type FurnitureId = usize;
+pub type IglooId = usize;
+pub type FloorId = usize;
+pub type LocationId = usize;
+pub type StampId = usize;
+pub type PostcardId = usize;
+
+/// Stored per penguin, only moderators (stealthy or not) get to kick, mute and ban
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModeratorStatus {
+    Mascot,
+    StealthModerator,
+    Moderator,
+    #[default]
+    None, //lol
+}
+
+impl ModeratorStatus {
+    // what the client expects in `js`, persisted as is
+    pub fn code(self) -> u8 {
+        match self {
+            ModeratorStatus::Mascot => 3,
+            ModeratorStatus::StealthModerator => 2,
+            ModeratorStatus::Moderator => 1,
+            ModeratorStatus::None => 0,
+        }
+    }
 
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            3 => Some(ModeratorStatus::Mascot),
+            2 => Some(ModeratorStatus::StealthModerator),
+            1 => Some(ModeratorStatus::Moderator),
+            0 => Some(ModeratorStatus::None),
+            _ => None,
+        }
+    }
+
+    pub fn can_moderate(self) -> bool {
+        matches!(
+            self,
+            ModeratorStatus::Moderator | ModeratorStatus::StealthModerator
+        )
+    }
+}
 
 // TODO: there seem to be four... no idea what they do
 #[derive(Debug, Clone, PartialEq)]
@@ -38,9 +90,9 @@ pub struct PlayerGist {
     pub member: bool,
     pub membership_days: u32,
     pub avatar: ItemId,
-    // TODO: NEEDED FOR AS3 BUT NOT AS2
-    // pub penguin_state: String,
-    // pub party_state: String,
+    // only sent to as3 clients, opaque to us for now
+    pub penguin_state: String,
+    pub party_state: String,
     pub puffle_state: PlayerPuffleGist,
 }
 
@@ -71,14 +123,63 @@ pub struct NewPlayerStatus{}
 //     HasWalkedPuffleFirstTime = 65536
 //     HasWalkedPuffleSecondTime = 131072
 
+/// An entry of the buddy list, online means in the same world
+#[derive(Debug, Clone, PartialEq)]
+pub struct Buddy {
+    pub id: PlayerId,
+    pub nickname: String,
+    pub online: bool,
+}
+
+/// A postcard in someone's mailbox
 #[derive(Debug, Clone, PartialEq)]
-pub struct PlayerPuffleGist {}
+pub struct Postcard {
+    pub id: PostcardId,
+    // id and nickname, None for postcards sent by the system
+    pub sender: Option<(PlayerId, String)>,
+    pub postcard_type: usize,
+    // filled into the postcard's text, mostly empty
+    pub details: String,
+    // unix timestamp in seconds
+    pub sent_at: u64,
+    pub has_read: bool,
+}
+
+/// What the server sends postcards about, see `config::SystemPostcard`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Occasion {
+    // the very first time the penguin joins a world
+    FirstLogin,
+    // announced by a moderator, the party's name comes along as details
+    PartyStarted,
+    // by a moderator, the reason comes along as details
+    Warned,
+}
+
+/// Puffle walked by the player, as3 only
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerPuffleGist {
+    pub walking: Option<WalkingPuffle>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkingPuffle {
+    pub id: PuffleId,
+    pub type_id: usize,
+    pub sub_type_id: usize,
+    pub hat: ItemId,
+}
 
 /// Serialized information used in communication
 pub trait IntoPlayerGistString {
     fn into_gist_string(self) -> String;
 }
 
+/* NOTE:
+ * This is the as2 flavour, the trailing states are always blank.
+ * as3 fills them in, see `pkt::xt::as3`
+ */
 impl IntoPlayerGistString for PlayerGist {
     fn into_gist_string(self) -> String {
         format!(
@@ -105,9 +206,7 @@ impl IntoPlayerGistString for PlayerGist {
             0, //self.penguin_state,
             0, //self.party_state,
             // TODO: implement for as3
-            match self.puffle_state {
-                PlayerPuffleGist {} => "||||",
-            } // self.puffle_state,
+            "||||", // self.puffle_state,
         )
     }
 }
@@ -137,10 +236,9 @@ mod tests {
             member: true,
             membership_days: 9,
             avatar: 0,
-            // TODO: IM
-            // penguin_state: "".to_owned(),
-            // party_state: "".to_owned(),
-            puffle_state: PlayerPuffleGist {},
+            penguin_state: "".to_owned(),
+            party_state: "".to_owned(),
+            puffle_state: PlayerPuffleGist::default(),
         };
         assert_eq!(
             player_gist.into_gist_string(),
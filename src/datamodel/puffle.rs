@@ -0,0 +1,33 @@
+use serde::Deserialize;
+
+use crate::datamodel::catalog::Record;
+
+/// A kind of puffle (blue, pink, ...), not somebody's pet
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Puffle {
+    #[serde(rename = "puffle_id")]
+    pub type_id: usize,
+    pub name: String,
+    #[serde(default)]
+    pub cost: usize,
+    #[serde(rename = "is_member", default)]
+    pub member: bool,
+    pub max_food: u8,
+    pub max_rest: u8,
+    pub max_clean: u8,
+}
+
+impl Record for Puffle {
+    const KIND: &'static str = "puffle";
+
+    fn id(&self) -> usize {
+        self.type_id
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.max_food == 0 || self.max_rest == 0 || self.max_clean == 0 {
+            anyhow::bail!("would starve right away");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::datamodel::RoomId;
+
+/* NOTE:
+ * Rooms follow the crumbs layout, keyed by id:
+ * {"100": {"room_id": 100, "room_key": "town", "name": "Town", "max_users": 80, "is_member": 0}}
+ * Crumbs don't flag games, a room is only one with an extra "game": true.
+ */
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Room {
+    pub id: RoomId,
+    pub key: String,
+    pub name: String,
+    pub capacity: usize,
+    pub member: bool,
+    pub game: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawRoom {
+    room_id: RoomId,
+    room_key: String,
+    name: String,
+    max_users: usize,
+    #[serde(default)]
+    is_member: u8,
+    #[serde(default)]
+    game: bool,
+}
+
+impl From<RawRoom> for Room {
+    fn from(raw: RawRoom) -> Self {
+        Self {
+            id: raw.room_id,
+            key: raw.room_key,
+            name: raw.name,
+            capacity: raw.max_users,
+            member: raw.is_member != 0,
+            game: raw.game,
+        }
+    }
+}
+
+/// Every room a world knows of, never changes at runtime
+#[derive(Debug, Clone, Default)]
+pub struct RoomCatalog(HashMap<RoomId, Room>);
+
+impl RoomCatalog {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read room catalog {}", path.display()))?;
+        Self::parse(&raw).with_context(|| format!("bad room catalog {}", path.display()))
+    }
+
+    pub fn parse(raw: &str) -> Result<Self> {
+        let raw: HashMap<String, RawRoom> =
+            serde_json::from_str(raw).context("failed to parse rooms")?;
+        let mut rooms = HashMap::with_capacity(raw.len());
+        for (key, room) in raw {
+            if key != room.room_id.to_string() {
+                anyhow::bail!("room {} is filed under \"{key}\"", room.room_id);
+            }
+            if room.max_users == 0 {
+                anyhow::bail!("room {} has no capacity", room.room_id);
+            }
+            rooms.insert(room.room_id, room.into());
+        }
+        Ok(Self(rooms))
+    }
+
+    pub fn get(&self, room_id: RoomId) -> Option<&Room> {
+        self.0.get(&room_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Room> {
+        self.0.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_crumbs() {
+        let rooms = RoomCatalog::parse(
+            r#"{
+                "100": {"room_id": 100, "room_key": "town", "name": "Town", "max_users": 80,
+                        "is_member": 0, "path": "town.swf"},
+                "121": {"room_id": 121, "room_key": "lounge", "name": "Dance Lounge",
+                        "max_users": 80, "is_member": 1},
+                "900": {"room_id": 900, "room_key": "astro", "name": "Astro Barrier",
+                        "max_users": 80, "game": true},
+                "950": {"room_id": 950, "room_key": "lobby", "name": "Lobby", "max_users": 80}
+            }"#,
+        )
+        .expect("failed to parse");
+        assert_eq!(rooms.len(), 4);
+        let town = rooms.get(100).unwrap();
+        assert_eq!((town.key.as_str(), town.capacity), ("town", 80));
+        assert!(!town.member && !town.game);
+        assert!(rooms.get(121).unwrap().member);
+        assert!(rooms.get(900).unwrap().game);
+        assert!(!rooms.get(950).unwrap().game);
+        assert!(rooms.get(101).is_none());
+    }
+
+    #[test]
+    fn rejects_misfiled_rooms() {
+        let raw =
+            r#"{"101": {"room_id": 100, "room_key": "town", "name": "Town", "max_users": 80}}"#;
+        assert!(RoomCatalog::parse(raw).is_err());
+    }
+
+    #[test]
+    fn shipped_rooms_are_valid() {
+        let rooms = RoomCatalog::parse(include_str!("../../data/rooms.json")).unwrap();
+        assert!(rooms.get(230).is_some());
+    }
+}
@@ -0,0 +1,31 @@
+use serde::Deserialize;
+
+use crate::datamodel::{catalog::Record, StampId};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Stamp {
+    #[serde(rename = "stamp_id")]
+    pub id: StampId,
+    pub name: String,
+    #[serde(rename = "is_member", default)]
+    pub member: bool,
+    // difficulty, 1 (easy) to 4 (extreme)
+    pub rank: u8,
+    #[serde(rename = "group_id")]
+    pub group: usize,
+}
+
+impl Record for Stamp {
+    const KIND: &'static str = "stamp";
+
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if !(1..=4).contains(&self.rank) {
+            anyhow::bail!("rank {} is not a thing", self.rank);
+        }
+        Ok(())
+    }
+}
@@ -1,25 +1,163 @@
+pub mod config;
 pub mod conn;
 pub mod datamodel;
 pub mod persistence;
 pub mod pkt;
 pub mod server;
 
-use std::time::Duration;
+use std::sync::Arc;
 
-use anyhow::{Context, Error, Result};
+use anyhow::{Context, Result};
+use clap::Parser;
 use env_logger::Env;
 
-use crate::conn::server_list;
+use crate::config::Config;
+use crate::conn::{
+    login_key::{KeyStore, DEFAULT_LOGIN_KEY_TTL},
+    server_list::{ServerList, World},
+};
+use crate::datamodel::catalog::Catalog;
+use crate::persistence::manager::{mem::MemoryManager, seed::Seed, sqlite::SqliteManager};
+
+/// Without any flags, everything in the config file is started.
+/// The login server and the worlds may also run as separate processes,
+/// as long as they share a database: login keys are handed over through it.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Path to the TOML config file
+    #[arg(long, value_name = "PATH", default_value = "config.toml")]
+    config: String,
+
+    /// Run the configured login server
+    #[arg(long)]
+    login: bool,
+
+    /// Run the configured world with this id, may be repeated
+    #[arg(long = "world", value_name = "ID")]
+    worlds: Vec<usize>,
+}
+
+async fn open_persistence(config: &config::PersistenceConfig) -> Result<persistence::Manager> {
+    let seed = config.seed.as_ref().map(Seed::from_file).transpose()?;
+    if config.database == ":memory:" {
+        log::warn!("using volatile in-memory persistence, nothing will be saved!");
+        let manager = match seed {
+            Some(seed) => MemoryManager::from_seed(seed)?,
+            None => MemoryManager::new(),
+        };
+        return Ok(Arc::new(manager));
+    }
+
+    log::info!("using sqlite database {}", config.database);
+    let manager = SqliteManager::open(&config.database)?;
+    if let Some(seed) = seed {
+        manager.import(seed).await?;
+    }
+    Ok(Arc::new(manager))
+}
 
 #[tokio::main()]
 async fn main() -> Result<()> {
     env_logger::Builder::from_env(Env::default().default_filter_or("debug")).init();
 
-    let server_tx = server::bind("0.0.0.0:1337").await?;
+    let args = Args::parse();
+    let config = Arc::new(Config::from_file(&args.config)?);
+
+    let run_everything = !args.login && args.worlds.is_empty();
+    let split = !(run_everything || args.login && args.worlds.len() == config.worlds.len());
+    if split && config.persistence.database == ":memory:" {
+        anyhow::bail!(
+            "--login and --world in separate processes need a shared database, not :memory:"
+        );
+    }
+    let login_config = if args.login {
+        let login = config.login.clone();
+        Some(login.context("--login given, but no [login] is configured")?)
+    } else if run_everything {
+        config.login.clone()
+    } else {
+        None
+    };
+    let world_configs = if run_everything {
+        config.worlds.clone()
+    } else {
+        args.worlds
+            .iter()
+            .map(|id| {
+                config
+                    .world(*id)
+                    .cloned()
+                    .with_context(|| format!("world {id} is not configured"))
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    let catalog = Arc::new(Catalog::load(&config.data)?);
+    log::info!(
+        "loaded {} rooms and {} items",
+        catalog.rooms.len(),
+        catalog.items.len()
+    );
+    if let Some(missing) = config
+        .gameplay
+        .spawn_rooms
+        .iter()
+        .find(|id| catalog.rooms.get(**id).is_none())
+    {
+        anyhow::bail!("spawn room {missing} is not in the room catalog");
+    }
+
+    let persistence = open_persistence(&config.persistence).await?;
+    // keys minted by the login server are redeemed by the worlds, through persistence
+    let ttl = config
+        .login
+        .as_ref()
+        .map_or(DEFAULT_LOGIN_KEY_TTL, |login| login.login_key_ttl());
+    let keys = KeyStore::new(persistence.clone(), ttl);
+
+    let mut world_handles = Vec::with_capacity(world_configs.len());
+    for world in world_configs {
+        log::info!(
+            "starting world {} ({}) on {}",
+            world.id,
+            world.name,
+            world.address
+        );
+        let handle = server::bind(
+            &world,
+            config.clone(),
+            catalog.clone(),
+            persistence.clone(),
+            keys.clone(),
+        )
+        .await
+        .with_context(|| format!("failed to start world {}", world.id))?;
+        world_handles.push(handle);
+    }
+
+    let login = match login_config {
+        Some(login) => Some(
+            ServerList::new(
+                persistence.clone(),
+                keys.clone(),
+                config.policy(),
+                config.worlds.iter().map(World::from).collect(),
+            )
+            .await?
+            .bind(&login.endpoints())
+            .await
+            .context("failed to start login server")?,
+        ),
+        None => None,
+    };
 
     tokio::signal::ctrl_c().await?;
     log::info!("terminating ...");
-    drop(server_tx);
+    if let Some(login) = login {
+        login.abort();
+    }
+    drop(world_handles);
     Ok(())
     // tokio::time::sleep(Duration::from_secs(600)).await;
     // drop(tx);
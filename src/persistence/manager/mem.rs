@@ -0,0 +1,588 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::RwLock;
+
+use crate::{
+    datamodel::{ItemId, PlayerId, Postcard, PostcardId, StampId, WorldId},
+    persistence::{
+        manager::{
+            seed::{Seed, SeedPenguin},
+            PersistenceManager,
+        },
+        Account, Ban, Igloo, LoginKey, Penguin, Stamp,
+    },
+};
+
+/// Volatile backend, everything is gone once the process exits.
+/// Good enough for test worlds, see `from_seed` for populating it.
+pub struct MemoryManager(RwLock<Store>);
+
+#[derive(Default)]
+struct Store {
+    accounts: HashMap<String, Account>,
+    penguins: HashMap<PlayerId, Penguin>,
+    inventories: HashMap<PlayerId, Vec<ItemId>>,
+    stamps: HashMap<PlayerId, Vec<Stamp>>,
+    igloos: HashMap<PlayerId, Igloo>,
+    buddies: HashMap<PlayerId, Vec<PlayerId>>,
+    ignores: HashMap<PlayerId, Vec<PlayerId>>,
+    // oldest first, ids keep counting up across all mailboxes
+    postcards: HashMap<PlayerId, Vec<Postcard>>,
+    last_postcard_id: PostcardId,
+    bans: Vec<Ban>,
+    login_keys: HashMap<PlayerId, LoginKey>,
+    presence: HashMap<PlayerId, WorldId>,
+}
+
+impl MemoryManager {
+    pub fn new() -> Self {
+        Self(RwLock::new(Store::default()))
+    }
+
+    pub fn from_seed(seed: Seed) -> Result<Self> {
+        let mut store = Store::default();
+        for SeedPenguin {
+            username,
+            password_hash,
+            inventory,
+            buddies,
+            penguin,
+        } in seed.penguins
+        {
+            if store.penguins.contains_key(&penguin.id) {
+                anyhow::bail!("penguin {} is seeded twice", penguin.id);
+            }
+            let account = Account {
+                id: penguin.id,
+                username: username.clone(),
+                password_hash,
+            };
+            if store.accounts.insert(username.clone(), account).is_some() {
+                anyhow::bail!("username {username} is seeded twice");
+            }
+            store.inventories.insert(penguin.id, inventory);
+            store.buddies.insert(penguin.id, buddies);
+            store.penguins.insert(penguin.id, penguin);
+        }
+        Ok(Self(RwLock::new(store)))
+    }
+}
+
+impl Default for MemoryManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl PersistenceManager for MemoryManager {
+    async fn load_account(&self, username: &str) -> Result<Option<Account>> {
+        Ok(self.0.read().await.accounts.get(username).cloned())
+    }
+
+    async fn load_penguin(&self, penguin_id: PlayerId) -> Result<Option<Penguin>> {
+        Ok(self.0.read().await.penguins.get(&penguin_id).cloned())
+    }
+
+    async fn save_penguin(&self, penguin: &Penguin) -> Result<()> {
+        let mut store = self.0.write().await;
+        match store.penguins.get_mut(&penguin.id) {
+            Some(stored) => *stored = penguin.clone(),
+            None => anyhow::bail!("penguin {} does not exist", penguin.id),
+        }
+        Ok(())
+    }
+
+    async fn list_inventory(&self, penguin_id: PlayerId) -> Result<Vec<ItemId>> {
+        Ok(self
+            .0
+            .read()
+            .await
+            .inventories
+            .get(&penguin_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn add_inventory_item(&self, penguin_id: PlayerId, item_id: ItemId) -> Result<()> {
+        let mut store = self.0.write().await;
+        if !store.penguins.contains_key(&penguin_id) {
+            anyhow::bail!("penguin {penguin_id} does not exist");
+        }
+        let inventory = store.inventories.entry(penguin_id).or_default();
+        if !inventory.contains(&item_id) {
+            inventory.push(item_id);
+        }
+        Ok(())
+    }
+
+    async fn list_stamps(&self, penguin_id: PlayerId) -> Result<Vec<Stamp>> {
+        Ok(self
+            .0
+            .read()
+            .await
+            .stamps
+            .get(&penguin_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn add_stamp(&self, penguin_id: PlayerId, stamp_id: StampId) -> Result<()> {
+        let mut store = self.0.write().await;
+        if !store.penguins.contains_key(&penguin_id) {
+            anyhow::bail!("penguin {penguin_id} does not exist");
+        }
+        let stamps = store.stamps.entry(penguin_id).or_default();
+        if !stamps.iter().any(|stamp| stamp.stamp_id == stamp_id) {
+            stamps.push(Stamp {
+                stamp_id,
+                recent: true,
+            });
+        }
+        Ok(())
+    }
+
+    async fn load_igloo(&self, penguin_id: PlayerId) -> Result<Option<Igloo>> {
+        Ok(self.0.read().await.igloos.get(&penguin_id).cloned())
+    }
+
+    async fn save_igloo(&self, igloo: &Igloo) -> Result<()> {
+        let mut store = self.0.write().await;
+        if !store.penguins.contains_key(&igloo.penguin_id) {
+            anyhow::bail!("penguin {} does not exist", igloo.penguin_id);
+        }
+        store.igloos.insert(igloo.penguin_id, igloo.clone());
+        Ok(())
+    }
+
+    async fn put_login_key(&self, key: &LoginKey) -> Result<()> {
+        let mut store = self.0.write().await;
+        if !store.penguins.contains_key(&key.penguin_id) {
+            anyhow::bail!("penguin {} does not exist", key.penguin_id);
+        }
+        store.login_keys.insert(key.penguin_id, key.clone());
+        Ok(())
+    }
+
+    async fn take_login_key(&self, penguin_id: PlayerId, key: &str) -> Result<Option<LoginKey>> {
+        let mut store = self.0.write().await;
+        if store
+            .login_keys
+            .get(&penguin_id)
+            .is_some_and(|pending| pending.key == key)
+        {
+            return Ok(store.login_keys.remove(&penguin_id));
+        }
+        Ok(None)
+    }
+
+    async fn has_login_key(&self, penguin_id: PlayerId) -> Result<bool> {
+        Ok(self.0.read().await.login_keys.contains_key(&penguin_id))
+    }
+
+    async fn prune_login_keys(&self, before: u64) -> Result<()> {
+        self.0
+            .write()
+            .await
+            .login_keys
+            .retain(|_, key| key.issued_at >= before);
+        Ok(())
+    }
+
+    async fn set_presence(&self, penguin_id: PlayerId, world_id: Option<WorldId>) -> Result<()> {
+        let mut store = self.0.write().await;
+        match world_id {
+            Some(world_id) => {
+                if !store.penguins.contains_key(&penguin_id) {
+                    anyhow::bail!("penguin {penguin_id} does not exist");
+                }
+                store.presence.insert(penguin_id, world_id);
+            }
+            None => {
+                store.presence.remove(&penguin_id);
+            }
+        }
+        Ok(())
+    }
+
+    async fn clear_presence(&self, world_id: WorldId) -> Result<()> {
+        self.0
+            .write()
+            .await
+            .presence
+            .retain(|_, world| *world != world_id);
+        Ok(())
+    }
+
+    async fn list_presence(&self, penguin_ids: Vec<PlayerId>) -> Result<Vec<(PlayerId, WorldId)>> {
+        let store = self.0.read().await;
+        Ok(penguin_ids
+            .into_iter()
+            .filter_map(|id| store.presence.get(&id).map(|world_id| (id, *world_id)))
+            .collect())
+    }
+
+    async fn world_populations(&self) -> Result<Vec<(WorldId, usize)>> {
+        let mut populations: HashMap<WorldId, usize> = HashMap::new();
+        for world_id in self.0.read().await.presence.values() {
+            *populations.entry(*world_id).or_default() += 1;
+        }
+        Ok(populations.into_iter().collect())
+    }
+
+    async fn add_ban(&self, ban: &Ban) -> Result<()> {
+        let mut store = self.0.write().await;
+        if !store.penguins.contains_key(&ban.penguin_id) {
+            anyhow::bail!("penguin {} does not exist", ban.penguin_id);
+        }
+        store.bans.push(ban.clone());
+        Ok(())
+    }
+
+    async fn active_ban(&self, penguin_id: PlayerId, now: u64) -> Result<Option<Ban>> {
+        Ok(self
+            .0
+            .read()
+            .await
+            .bans
+            .iter()
+            .filter(|ban| ban.penguin_id == penguin_id)
+            .filter(|ban| ban.expires_at.is_none_or(|expires_at| expires_at > now))
+            .max_by_key(|ban| ban.expires_at.unwrap_or(u64::MAX))
+            .cloned())
+    }
+
+    async fn list_buddies(&self, penguin_id: PlayerId) -> Result<Vec<PlayerId>> {
+        Ok(self
+            .0
+            .read()
+            .await
+            .buddies
+            .get(&penguin_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn add_buddy(&self, penguin_id: PlayerId, buddy_id: PlayerId) -> Result<()> {
+        let mut store = self.0.write().await;
+        for (a, b) in [(penguin_id, buddy_id), (buddy_id, penguin_id)] {
+            if !store.penguins.contains_key(&a) {
+                anyhow::bail!("penguin {a} does not exist");
+            }
+            let buddies = store.buddies.entry(a).or_default();
+            if !buddies.contains(&b) {
+                buddies.push(b);
+            }
+        }
+        Ok(())
+    }
+
+    async fn remove_buddy(&self, penguin_id: PlayerId, buddy_id: PlayerId) -> Result<()> {
+        let mut store = self.0.write().await;
+        for (a, b) in [(penguin_id, buddy_id), (buddy_id, penguin_id)] {
+            if let Some(buddies) = store.buddies.get_mut(&a) {
+                buddies.retain(|id| *id != b);
+            }
+        }
+        Ok(())
+    }
+
+    async fn list_ignored(&self, penguin_id: PlayerId) -> Result<Vec<PlayerId>> {
+        Ok(self
+            .0
+            .read()
+            .await
+            .ignores
+            .get(&penguin_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn add_ignore(&self, penguin_id: PlayerId, ignore_id: PlayerId) -> Result<()> {
+        let mut store = self.0.write().await;
+        for id in [penguin_id, ignore_id] {
+            if !store.penguins.contains_key(&id) {
+                anyhow::bail!("penguin {id} does not exist");
+            }
+        }
+        let ignores = store.ignores.entry(penguin_id).or_default();
+        if !ignores.contains(&ignore_id) {
+            ignores.push(ignore_id);
+        }
+        Ok(())
+    }
+
+    async fn remove_ignore(&self, penguin_id: PlayerId, ignore_id: PlayerId) -> Result<()> {
+        if let Some(ignores) = self.0.write().await.ignores.get_mut(&penguin_id) {
+            ignores.retain(|id| *id != ignore_id);
+        }
+        Ok(())
+    }
+
+    async fn send_postcard(
+        &self,
+        recipient_id: PlayerId,
+        postcard: &Postcard,
+        mailbox_size: usize,
+    ) -> Result<Option<PostcardId>> {
+        let mut store = self.0.write().await;
+        let sender_id = postcard.sender.as_ref().map(|(id, _)| *id);
+        for id in std::iter::once(recipient_id).chain(sender_id) {
+            if !store.penguins.contains_key(&id) {
+                anyhow::bail!("penguin {id} does not exist");
+            }
+        }
+        if store.postcards.get(&recipient_id).map_or(0, Vec::len) >= mailbox_size {
+            return Ok(None);
+        }
+        store.last_postcard_id += 1;
+        let id = store.last_postcard_id;
+        store
+            .postcards
+            .entry(recipient_id)
+            .or_default()
+            .push(Postcard {
+                id,
+                ..postcard.clone()
+            });
+        Ok(Some(id))
+    }
+
+    async fn list_postcards(&self, recipient_id: PlayerId) -> Result<Vec<Postcard>> {
+        let store = self.0.read().await;
+        Ok(store
+            .postcards
+            .get(&recipient_id)
+            .into_iter()
+            .flatten()
+            .rev()
+            .map(|postcard| Postcard {
+                // whatever the sender is called today
+                sender: postcard.sender.as_ref().and_then(|(id, _)| {
+                    store
+                        .penguins
+                        .get(id)
+                        .map(|sender| (*id, sender.nickname.clone()))
+                }),
+                ..postcard.clone()
+            })
+            .collect())
+    }
+
+    async fn read_postcards(&self, recipient_id: PlayerId) -> Result<()> {
+        if let Some(postcards) = self.0.write().await.postcards.get_mut(&recipient_id) {
+            for postcard in postcards {
+                postcard.has_read = true;
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_postcard(&self, recipient_id: PlayerId, postcard_id: PostcardId) -> Result<()> {
+        if let Some(postcards) = self.0.write().await.postcards.get_mut(&recipient_id) {
+            postcards.retain(|postcard| postcard.id != postcard_id);
+        }
+        Ok(())
+    }
+
+    async fn delete_postcards_from(
+        &self,
+        recipient_id: PlayerId,
+        sender_id: PlayerId,
+    ) -> Result<()> {
+        if let Some(postcards) = self.0.write().await.postcards.get_mut(&recipient_id) {
+            postcards
+                .retain(|postcard| postcard.sender.as_ref().map(|(id, _)| *id) != Some(sender_id));
+        }
+        Ok(())
+    }
+
+    async fn nicknames(&self, penguin_ids: Vec<PlayerId>) -> Result<Vec<(PlayerId, String)>> {
+        let store = self.0.read().await;
+        Ok(penguin_ids
+            .into_iter()
+            .filter_map(|id| store.penguins.get(&id).map(|p| (id, p.nickname.clone())))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: &str = r#"{"penguins": [
+        {"id": 102, "username": "kirill", "password_hash": "acbd18db4cc2f85cedef654fccc4a4d8",
+         "nickname": "Kirill", "coins": 100, "color": 1, "head": 429, "inventory": [1, 429],
+         "buddies": [103]},
+        {"id": 103, "username": "basil", "password_hash": "37b51d194a7513e45b56f6524f2d51f2",
+         "nickname": "Basil"}
+    ]}"#;
+
+    #[tokio::test]
+    async fn seeded_lookup() {
+        let manager = MemoryManager::from_seed(Seed::parse(SEED).unwrap()).expect("failed to seed");
+
+        let account = manager.load_account("kirill").await.unwrap().unwrap();
+        assert_eq!(account.id, 102);
+        assert!(manager.load_account("nobody").await.unwrap().is_none());
+
+        let penguin = manager.load_penguin(103).await.unwrap().unwrap();
+        assert_eq!(penguin.nickname, "Basil");
+        assert_eq!(penguin.coins, 0);
+
+        assert_eq!(manager.list_inventory(102).await.unwrap(), vec![1, 429]);
+        assert!(manager.list_inventory(103).await.unwrap().is_empty());
+        assert_eq!(manager.list_buddies(102).await.unwrap(), vec![103]);
+        assert_eq!(
+            manager.nicknames(vec![103, 404]).await.unwrap(),
+            vec![(103, "Basil".to_owned())]
+        );
+    }
+
+    #[tokio::test]
+    async fn stamps_and_igloos() {
+        let manager = MemoryManager::from_seed(Seed::parse(SEED).unwrap()).expect("failed to seed");
+        manager.add_stamp(103, 14).await.unwrap();
+        manager.add_stamp(103, 14).await.unwrap();
+        assert_eq!(
+            manager.list_stamps(103).await.unwrap(),
+            vec![Stamp {
+                stamp_id: 14,
+                recent: true
+            }]
+        );
+        assert!(manager.add_stamp(404, 14).await.is_err());
+
+        assert_eq!(manager.load_igloo(103).await.unwrap(), None);
+        let mut igloo = Igloo {
+            penguin_id: 103,
+            igloo_type: 2,
+            floor: 4,
+            music: 0,
+            location: 1,
+            locked: true,
+            furniture: vec![crate::persistence::PlacedFurniture {
+                furniture_id: 305,
+                x: 100,
+                y: 240,
+                rotation: 3,
+                frame: 1,
+            }],
+        };
+        manager.save_igloo(&igloo).await.unwrap();
+        igloo.furniture.clear();
+        manager.save_igloo(&igloo).await.unwrap();
+        assert_eq!(manager.load_igloo(103).await.unwrap(), Some(igloo.clone()));
+        igloo.penguin_id = 404;
+        assert!(manager.save_igloo(&igloo).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn buddies_both_ways() {
+        let manager = MemoryManager::from_seed(Seed::parse(SEED).unwrap()).expect("failed to seed");
+        manager.add_buddy(103, 102).await.unwrap();
+        assert_eq!(manager.list_buddies(102).await.unwrap(), vec![103]);
+        assert_eq!(manager.list_buddies(103).await.unwrap(), vec![102]);
+
+        manager.remove_buddy(102, 103).await.unwrap();
+        assert!(manager.list_buddies(102).await.unwrap().is_empty());
+        assert!(manager.list_buddies(103).await.unwrap().is_empty());
+        assert!(manager.add_buddy(102, 404).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn ignores_one_way() {
+        let manager = MemoryManager::from_seed(Seed::parse(SEED).unwrap()).expect("failed to seed");
+        manager.add_ignore(102, 103).await.unwrap();
+        manager.add_ignore(102, 103).await.unwrap();
+        assert_eq!(manager.list_ignored(102).await.unwrap(), vec![103]);
+        assert!(manager.list_ignored(103).await.unwrap().is_empty());
+
+        manager.remove_ignore(102, 103).await.unwrap();
+        assert!(manager.list_ignored(102).await.unwrap().is_empty());
+        assert!(manager.add_ignore(102, 404).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn mailbox() {
+        let manager = MemoryManager::from_seed(Seed::parse(SEED).unwrap()).expect("failed to seed");
+        let postcard = |sender| Postcard {
+            id: 0,
+            sender,
+            postcard_type: 1,
+            details: String::new(),
+            sent_at: 1000,
+            has_read: false,
+        };
+        let from_kirill = manager
+            .send_postcard(103, &postcard(Some((102, String::new()))), 2)
+            .await
+            .unwrap()
+            .unwrap();
+        let from_system = manager
+            .send_postcard(103, &postcard(None), 2)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            manager
+                .send_postcard(103, &postcard(None), 2)
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(manager
+            .send_postcard(404, &postcard(None), 2)
+            .await
+            .is_err());
+
+        let mailbox = manager.list_postcards(103).await.unwrap();
+        assert_eq!(
+            mailbox.iter().map(|p| p.id).collect::<Vec<_>>(),
+            vec![from_system, from_kirill]
+        );
+        assert_eq!(mailbox[1].sender, Some((102, "Kirill".to_owned())));
+
+        manager.read_postcards(103).await.unwrap();
+        assert!(manager
+            .list_postcards(103)
+            .await
+            .unwrap()
+            .iter()
+            .all(|p| p.has_read));
+        manager.delete_postcards_from(103, 102).await.unwrap();
+        assert_eq!(manager.list_postcards(103).await.unwrap().len(), 1);
+        manager.delete_postcard(103, from_system).await.unwrap();
+        assert!(manager.list_postcards(103).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn save_roundtrip() {
+        let manager = MemoryManager::from_seed(Seed::parse(SEED).unwrap()).expect("failed to seed");
+        let mut penguin = manager.load_penguin(102).await.unwrap().unwrap();
+        penguin.coins = 5;
+        manager.save_penguin(&penguin).await.unwrap();
+        assert_eq!(manager.load_penguin(102).await.unwrap().unwrap().coins, 5);
+
+        penguin.id = 404;
+        assert!(manager.save_penguin(&penguin).await.is_err());
+
+        manager.add_inventory_item(102, 9057).await.unwrap();
+        manager.add_inventory_item(102, 9057).await.unwrap();
+        assert_eq!(
+            manager.list_inventory(102).await.unwrap(),
+            vec![1, 429, 9057]
+        );
+    }
+
+    #[test]
+    fn duplicate_seed() {
+        let raw = r#"{"penguins": [
+            {"id": 1, "username": "a", "password_hash": "", "nickname": "A"},
+            {"id": 2, "username": "a", "password_hash": "", "nickname": "B"}
+        ]}"#;
+        assert!(MemoryManager::from_seed(Seed::parse(raw).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+pub mod mem;
+pub mod seed;
+pub mod sqlite;
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{
+    datamodel::{ItemId, PlayerId, Postcard, PostcardId, StampId, WorldId},
+    persistence::{Account, Ban, Igloo, LoginKey, Penguin, Stamp},
+};
+
+/* NOTE:
+ * "not found" is not an error, it is a perfectly valid answer
+ * (someone typed the wrong username).
+ * Err(..) is reserved for the backend itself failing.
+ */
+#[async_trait]
+pub trait PersistenceManager: Send + Sync {
+    async fn load_account(&self, username: &str) -> Result<Option<Account>>;
+
+    async fn load_penguin(&self, penguin_id: PlayerId) -> Result<Option<Penguin>>;
+
+    async fn save_penguin(&self, penguin: &Penguin) -> Result<()>;
+
+    async fn list_inventory(&self, penguin_id: PlayerId) -> Result<Vec<ItemId>>;
+
+    /// Owning an item twice is not a thing, adding it again does nothing
+    async fn add_inventory_item(&self, penguin_id: PlayerId, item_id: ItemId) -> Result<()>;
+
+    /// In the order they were earned
+    async fn list_stamps(&self, penguin_id: PlayerId) -> Result<Vec<Stamp>>;
+
+    /// Earning a stamp twice is not a thing either, it arrives as recent
+    async fn add_stamp(&self, penguin_id: PlayerId, stamp_id: StampId) -> Result<()>;
+
+    /// None until the penguin's igloo was first saved
+    async fn load_igloo(&self, penguin_id: PlayerId) -> Result<Option<Igloo>>;
+
+    /// The furniture laid out before is replaced along with the rest
+    async fn save_igloo(&self, igloo: &Igloo) -> Result<()>;
+
+    /// Any older key of the penguin is replaced
+    async fn put_login_key(&self, key: &LoginKey) -> Result<()>;
+
+    /// Removes the key as it is handed out, two callers never get the same one.
+    /// None, and the pending key left alone, unless `key` is the one issued.
+    async fn take_login_key(&self, penguin_id: PlayerId, key: &str) -> Result<Option<LoginKey>>;
+
+    async fn has_login_key(&self, penguin_id: PlayerId) -> Result<bool>;
+
+    /// Drops every key issued before `before` (unix seconds)
+    async fn prune_login_keys(&self, before: u64) -> Result<()>;
+
+    /// Which world the penguin is on, None once it left
+    async fn set_presence(&self, penguin_id: PlayerId, world_id: Option<WorldId>) -> Result<()>;
+
+    /// A starting world forgets whoever it had before it went down
+    async fn clear_presence(&self, world_id: WorldId) -> Result<()>;
+
+    /// Penguins that are on no world are left out
+    async fn list_presence(&self, penguin_ids: Vec<PlayerId>) -> Result<Vec<(PlayerId, WorldId)>>;
+
+    /// Worlds nobody is on are left out
+    async fn world_populations(&self) -> Result<Vec<(WorldId, usize)>>;
+
+    async fn add_ban(&self, ban: &Ban) -> Result<()>;
+
+    /// The ban lasting the longest at `now` (unix seconds), if any
+    async fn active_ban(&self, penguin_id: PlayerId, now: u64) -> Result<Option<Ban>>;
+
+    async fn list_buddies(&self, penguin_id: PlayerId) -> Result<Vec<PlayerId>>;
+
+    /// Buddies go both ways, so do these two
+    async fn add_buddy(&self, penguin_id: PlayerId, buddy_id: PlayerId) -> Result<()>;
+
+    async fn remove_buddy(&self, penguin_id: PlayerId, buddy_id: PlayerId) -> Result<()>;
+
+    async fn list_ignored(&self, penguin_id: PlayerId) -> Result<Vec<PlayerId>>;
+
+    /// Unlike buddies, ignoring someone is one-sided
+    async fn add_ignore(&self, penguin_id: PlayerId, ignore_id: PlayerId) -> Result<()>;
+
+    async fn remove_ignore(&self, penguin_id: PlayerId, ignore_id: PlayerId) -> Result<()>;
+
+    /// Stores a postcard for `recipient_id`, its id and the sender's nickname are ignored.
+    /// None if `recipient_id` already holds `mailbox_size` postcards,
+    /// counted and stored in one go so two senders cannot both squeeze in.
+    async fn send_postcard(
+        &self,
+        recipient_id: PlayerId,
+        postcard: &Postcard,
+        mailbox_size: usize,
+    ) -> Result<Option<PostcardId>>;
+
+    /// Newest first
+    async fn list_postcards(&self, recipient_id: PlayerId) -> Result<Vec<Postcard>>;
+
+    /// Marks the whole mailbox as read
+    async fn read_postcards(&self, recipient_id: PlayerId) -> Result<()>;
+
+    async fn delete_postcard(&self, recipient_id: PlayerId, postcard_id: PostcardId) -> Result<()>;
+
+    async fn delete_postcards_from(
+        &self,
+        recipient_id: PlayerId,
+        sender_id: PlayerId,
+    ) -> Result<()>;
+
+    /// Penguins that don't exist (anymore) are left out
+    async fn nicknames(&self, penguin_ids: Vec<PlayerId>) -> Result<Vec<(PlayerId, String)>>;
+}
+
+/// Shared handle, cheap to clone into every system
+pub type Manager = Arc<dyn PersistenceManager>;
@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{
+    datamodel::{ItemId, PlayerId},
+    persistence::Penguin,
+};
+
+/* seed file layout:
+ * {"penguins": [
+ *     {"id": 102, "username": "kirill", "password_hash": "<md5>",
+ *      "nickname": "Kirill", "coins": 100, "color": 1, "inventory": [1],
+ *      "moderator": "moderator",
+ *      "buddies": [103]}
+ * ]}
+ */
+#[derive(Debug, Deserialize)]
+pub struct Seed {
+    pub penguins: Vec<SeedPenguin>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SeedPenguin {
+    pub username: String,
+    pub password_hash: String,
+    #[serde(default)]
+    pub inventory: Vec<ItemId>,
+    // one-sided, list the relationship on both penguins
+    #[serde(default)]
+    pub buddies: Vec<PlayerId>,
+    #[serde(flatten)]
+    pub penguin: Penguin,
+}
+
+impl Seed {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read seed file {}", path.display()))?;
+        Self::parse(&raw).with_context(|| format!("bad seed file {}", path.display()))
+    }
+
+    pub fn parse(raw: &str) -> Result<Self> {
+        serde_json::from_str(raw).context("failed to parse seed")
+    }
+}
@@ -0,0 +1,75 @@
+CREATE TABLE penguin (
+    id              INTEGER PRIMARY KEY,
+    username        TEXT    NOT NULL UNIQUE,
+    password_hash   TEXT    NOT NULL,
+    nickname        TEXT    NOT NULL,
+    coins           INTEGER NOT NULL DEFAULT 0,
+    safe_chat       INTEGER NOT NULL DEFAULT 0,
+    minutes_played  INTEGER NOT NULL DEFAULT 0,
+    registered_at   INTEGER NOT NULL DEFAULT 0,
+    color           INTEGER NOT NULL DEFAULT 0,
+    head            INTEGER NOT NULL DEFAULT 0,
+    face            INTEGER NOT NULL DEFAULT 0,
+    neck            INTEGER NOT NULL DEFAULT 0,
+    body            INTEGER NOT NULL DEFAULT 0,
+    hand            INTEGER NOT NULL DEFAULT 0,
+    feet            INTEGER NOT NULL DEFAULT 0,
+    flag            INTEGER NOT NULL DEFAULT 0,
+    photo           INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE inventory (
+    penguin_id  INTEGER NOT NULL REFERENCES penguin (id) ON DELETE CASCADE,
+    item_id     INTEGER NOT NULL,
+    PRIMARY KEY (penguin_id, item_id)
+);
+
+CREATE TABLE buddy (
+    penguin_id  INTEGER NOT NULL REFERENCES penguin (id) ON DELETE CASCADE,
+    buddy_id    INTEGER NOT NULL REFERENCES penguin (id) ON DELETE CASCADE,
+    PRIMARY KEY (penguin_id, buddy_id)
+);
+
+CREATE TABLE ignore (
+    penguin_id  INTEGER NOT NULL REFERENCES penguin (id) ON DELETE CASCADE,
+    ignore_id   INTEGER NOT NULL REFERENCES penguin (id) ON DELETE CASCADE,
+    PRIMARY KEY (penguin_id, ignore_id)
+);
+
+CREATE TABLE postcard (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    recipient_id    INTEGER NOT NULL REFERENCES penguin (id) ON DELETE CASCADE,
+    -- NULL for postcards sent by the system
+    sender_id       INTEGER REFERENCES penguin (id) ON DELETE SET NULL,
+    postcard_type   INTEGER NOT NULL,
+    details         TEXT    NOT NULL DEFAULT '',
+    sent_at         INTEGER NOT NULL,
+    has_read        INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX postcard_recipient ON postcard (recipient_id);
+
+CREATE TABLE stamp (
+    penguin_id  INTEGER NOT NULL REFERENCES penguin (id) ON DELETE CASCADE,
+    stamp_id    INTEGER NOT NULL,
+    recent      INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (penguin_id, stamp_id)
+);
+
+CREATE TABLE igloo (
+    penguin_id  INTEGER PRIMARY KEY REFERENCES penguin (id) ON DELETE CASCADE,
+    igloo_type  INTEGER NOT NULL DEFAULT 1,
+    floor       INTEGER NOT NULL DEFAULT 0,
+    music       INTEGER NOT NULL DEFAULT 0,
+    location    INTEGER NOT NULL DEFAULT 1,
+    locked      INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE TABLE igloo_furniture (
+    penguin_id      INTEGER NOT NULL REFERENCES penguin (id) ON DELETE CASCADE,
+    furniture_id    INTEGER NOT NULL,
+    x               INTEGER NOT NULL DEFAULT 0,
+    y               INTEGER NOT NULL DEFAULT 0,
+    rotation        INTEGER NOT NULL DEFAULT 1,
+    frame           INTEGER NOT NULL DEFAULT 1
+);
+CREATE INDEX igloo_furniture_penguin ON igloo_furniture (penguin_id);
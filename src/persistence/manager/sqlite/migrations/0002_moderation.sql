@@ -0,0 +1,14 @@
+-- ModeratorStatus::code, 0 for regular penguins
+ALTER TABLE penguin ADD COLUMN moderator INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE ban (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    penguin_id      INTEGER NOT NULL REFERENCES penguin (id) ON DELETE CASCADE,
+    issued_at       INTEGER NOT NULL,
+    -- NULL for bans that never run out
+    expires_at      INTEGER,
+    -- NULL for automatic bans
+    moderator_id    INTEGER REFERENCES penguin (id) ON DELETE SET NULL,
+    reason          TEXT    NOT NULL DEFAULT ''
+);
+CREATE INDEX ban_penguin ON ban (penguin_id);
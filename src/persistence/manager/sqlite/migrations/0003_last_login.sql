@@ -0,0 +1,2 @@
+-- unix timestamp in seconds, NULL until the penguin first joins a world
+ALTER TABLE penguin ADD COLUMN last_login_at INTEGER;
@@ -0,0 +1,8 @@
+-- at most one per penguin, on its way from the login server to a world
+CREATE TABLE login_key (
+    penguin_id      INTEGER PRIMARY KEY REFERENCES penguin (id) ON DELETE CASCADE,
+    key             TEXT    NOT NULL,
+    issued_at       INTEGER NOT NULL,
+    -- taken by a world's handshake, JoinServer is still due
+    redeemed        INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,6 @@
+-- who is on which world, so the login server doesn't have to ask
+CREATE TABLE presence (
+    penguin_id      INTEGER PRIMARY KEY REFERENCES penguin (id) ON DELETE CASCADE,
+    world_id        INTEGER NOT NULL
+);
+CREATE INDEX presence_world ON presence (world_id);
@@ -0,0 +1,1052 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::{
+    datamodel::{ItemId, ModeratorStatus, PlayerId, Postcard, PostcardId, StampId, WorldId},
+    persistence::{
+        manager::{seed::Seed, PersistenceManager},
+        Account, Ban, Igloo, LoginKey, Penguin, PlacedFurniture, Stamp,
+    },
+};
+
+/* NOTE:
+ * Migrations are applied in order and tracked through `PRAGMA user_version`,
+ * the version being the amount of migrations applied so far.
+ * Never edit a migration that has shipped, append a new one instead!
+ */
+const MIGRATIONS: &[&str] = &[
+    include_str!("migrations/0001_init.sql"),
+    include_str!("migrations/0002_moderation.sql"),
+    include_str!("migrations/0003_last_login.sql"),
+    include_str!("migrations/0004_login_key.sql"),
+    include_str!("migrations/0005_presence.sql"),
+];
+
+const PENGUIN_COLUMNS: &str =
+    "id, nickname, coins, safe_chat, moderator, minutes_played, registered_at, last_login_at, \
+     color, head, face, neck, body, hand, feet, flag, photo";
+
+/// Durable backend, a single sqlite file next to the server
+#[derive(Clone)]
+pub struct SqliteManager(Arc<Mutex<Connection>>);
+
+impl SqliteManager {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let conn = Connection::open(path)
+            .with_context(|| format!("failed to open database {}", path.display()))?;
+        Self::from_connection(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut conn: Connection) -> Result<Self> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        migrate(&mut conn).context("failed to migrate database")?;
+        Ok(Self(Arc::new(Mutex::new(conn))))
+    }
+
+    /// Insert seeded penguins, those that already exist are left untouched
+    pub async fn import(&self, seed: Seed) -> Result<()> {
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            for seeded in &seed.penguins {
+                let p = &seeded.penguin;
+                tx.execute(
+                    "INSERT OR IGNORE INTO penguin (id, username, password_hash, nickname, coins, \
+                     safe_chat, minutes_played, registered_at, color, head, face, neck, body, \
+                     hand, feet, flag, photo, moderator) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, \
+                     ?18)",
+                    params![
+                        p.id,
+                        seeded.username,
+                        seeded.password_hash,
+                        p.nickname,
+                        p.coins,
+                        p.safe_chat,
+                        p.minutes_played,
+                        p.registered_at,
+                        p.color,
+                        p.head,
+                        p.face,
+                        p.neck,
+                        p.body,
+                        p.hand,
+                        p.feet,
+                        p.flag,
+                        p.photo,
+                        p.moderator.code(),
+                    ],
+                )?;
+                for item in &seeded.inventory {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO inventory (penguin_id, item_id) VALUES (?1, ?2)",
+                        params![p.id, item],
+                    )?;
+                }
+            }
+            // second pass, buddies may reference penguins seeded later on
+            for seeded in &seed.penguins {
+                for buddy in &seeded.buddies {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO buddy (penguin_id, buddy_id) VALUES (?1, ?2)",
+                        params![seeded.penguin.id, buddy],
+                    )?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+        .context("failed to import seed")
+    }
+
+    // rusqlite is blocking, keep it off the runtime threads
+    async fn run<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let conn = self.0.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().expect("sqlite connection poisoned");
+            f(&mut conn)
+        })
+        .await
+        .context("sqlite worker died")?
+    }
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        anyhow::bail!(
+            "database is at schema version {version}, this build only knows {}",
+            MIGRATIONS.len()
+        );
+    }
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let version = i + 1;
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)
+            .with_context(|| format!("migration {version} failed"))?;
+        tx.pragma_update(None, "user_version", version)?;
+        tx.commit()?;
+        log::info!("applied database migration {version}");
+    }
+    Ok(())
+}
+
+fn penguin_from_row(row: &Row) -> rusqlite::Result<Penguin> {
+    Ok(Penguin {
+        id: row.get("id")?,
+        nickname: row.get("nickname")?,
+        coins: row.get("coins")?,
+        safe_chat: row.get("safe_chat")?,
+        // unknown codes come from a newer build, they get no powers here
+        moderator: ModeratorStatus::from_code(row.get("moderator")?).unwrap_or_default(),
+        minutes_played: row.get("minutes_played")?,
+        registered_at: row.get("registered_at")?,
+        last_login_at: row.get("last_login_at")?,
+        color: row.get("color")?,
+        head: row.get("head")?,
+        face: row.get("face")?,
+        neck: row.get("neck")?,
+        body: row.get("body")?,
+        hand: row.get("hand")?,
+        feet: row.get("feet")?,
+        flag: row.get("flag")?,
+        photo: row.get("photo")?,
+    })
+}
+
+#[async_trait]
+impl PersistenceManager for SqliteManager {
+    async fn load_account(&self, username: &str) -> Result<Option<Account>> {
+        let username = username.to_owned();
+        self.run(move |conn| {
+            conn.query_row(
+                "SELECT id, username, password_hash FROM penguin WHERE username = ?1",
+                params![username],
+                |row| {
+                    Ok(Account {
+                        id: row.get(0)?,
+                        username: row.get(1)?,
+                        password_hash: row.get(2)?,
+                    })
+                },
+            )
+            .optional()
+            .context("failed to query account")
+        })
+        .await
+    }
+
+    async fn load_penguin(&self, penguin_id: PlayerId) -> Result<Option<Penguin>> {
+        self.run(move |conn| {
+            conn.query_row(
+                &format!("SELECT {PENGUIN_COLUMNS} FROM penguin WHERE id = ?1"),
+                params![penguin_id],
+                penguin_from_row,
+            )
+            .optional()
+            .context("failed to query penguin")
+        })
+        .await
+    }
+
+    async fn save_penguin(&self, penguin: &Penguin) -> Result<()> {
+        let p = penguin.clone();
+        self.run(move |conn| {
+            let updated = conn.execute(
+                "UPDATE penguin SET nickname = ?2, coins = ?3, safe_chat = ?4, \
+                 minutes_played = ?5, registered_at = ?6, color = ?7, head = ?8, face = ?9, \
+                 neck = ?10, body = ?11, hand = ?12, feet = ?13, flag = ?14, photo = ?15, \
+                 moderator = ?16, last_login_at = ?17 WHERE id = ?1",
+                params![
+                    p.id,
+                    p.nickname,
+                    p.coins,
+                    p.safe_chat,
+                    p.minutes_played,
+                    p.registered_at,
+                    p.color,
+                    p.head,
+                    p.face,
+                    p.neck,
+                    p.body,
+                    p.hand,
+                    p.feet,
+                    p.flag,
+                    p.photo,
+                    p.moderator.code(),
+                    p.last_login_at,
+                ],
+            )?;
+            if updated == 0 {
+                anyhow::bail!("penguin {} does not exist", p.id);
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list_inventory(&self, penguin_id: PlayerId) -> Result<Vec<ItemId>> {
+        self.run(move |conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT item_id FROM inventory WHERE penguin_id = ?1 ORDER BY rowid",
+            )?;
+            let items = stmt
+                .query_map(params![penguin_id], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<ItemId>>>()?;
+            Ok(items)
+        })
+        .await
+    }
+
+    async fn add_inventory_item(&self, penguin_id: PlayerId, item_id: ItemId) -> Result<()> {
+        self.run(move |conn| {
+            conn.execute(
+                "INSERT OR IGNORE INTO inventory (penguin_id, item_id) VALUES (?1, ?2)",
+                params![penguin_id, item_id],
+            )
+            .context("failed to add inventory item")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list_stamps(&self, penguin_id: PlayerId) -> Result<Vec<Stamp>> {
+        self.run(move |conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT stamp_id, recent FROM stamp WHERE penguin_id = ?1 ORDER BY rowid",
+            )?;
+            let stamps = stmt
+                .query_map(params![penguin_id], |row| {
+                    Ok(Stamp {
+                        stamp_id: row.get(0)?,
+                        recent: row.get(1)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(stamps)
+        })
+        .await
+    }
+
+    async fn add_stamp(&self, penguin_id: PlayerId, stamp_id: StampId) -> Result<()> {
+        self.run(move |conn| {
+            conn.execute(
+                "INSERT OR IGNORE INTO stamp (penguin_id, stamp_id) VALUES (?1, ?2)",
+                params![penguin_id, stamp_id],
+            )
+            .context("failed to add stamp")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn load_igloo(&self, penguin_id: PlayerId) -> Result<Option<Igloo>> {
+        self.run(move |conn| {
+            let Some(mut igloo) = conn
+                .query_row(
+                    "SELECT igloo_type, floor, music, location, locked FROM igloo \
+                     WHERE penguin_id = ?1",
+                    params![penguin_id],
+                    |row| {
+                        Ok(Igloo {
+                            penguin_id,
+                            igloo_type: row.get(0)?,
+                            floor: row.get(1)?,
+                            music: row.get(2)?,
+                            location: row.get(3)?,
+                            locked: row.get(4)?,
+                            furniture: Vec::new(),
+                        })
+                    },
+                )
+                .optional()
+                .context("failed to load igloo")?
+            else {
+                return Ok(None);
+            };
+            let mut stmt = conn.prepare_cached(
+                "SELECT furniture_id, x, y, rotation, frame FROM igloo_furniture \
+                 WHERE penguin_id = ?1 ORDER BY rowid",
+            )?;
+            igloo.furniture = stmt
+                .query_map(params![penguin_id], |row| {
+                    Ok(PlacedFurniture {
+                        furniture_id: row.get(0)?,
+                        x: row.get(1)?,
+                        y: row.get(2)?,
+                        rotation: row.get(3)?,
+                        frame: row.get(4)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(Some(igloo))
+        })
+        .await
+    }
+
+    async fn save_igloo(&self, igloo: &Igloo) -> Result<()> {
+        let igloo = igloo.clone();
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT OR REPLACE INTO igloo \
+                 (penguin_id, igloo_type, floor, music, location, locked) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    igloo.penguin_id,
+                    igloo.igloo_type,
+                    igloo.floor,
+                    igloo.music,
+                    igloo.location,
+                    igloo.locked,
+                ],
+            )
+            .context("failed to save igloo")?;
+            tx.execute(
+                "DELETE FROM igloo_furniture WHERE penguin_id = ?1",
+                params![igloo.penguin_id],
+            )?;
+            for placed in &igloo.furniture {
+                tx.execute(
+                    "INSERT INTO igloo_furniture (penguin_id, furniture_id, x, y, rotation, frame) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        igloo.penguin_id,
+                        placed.furniture_id,
+                        placed.x,
+                        placed.y,
+                        placed.rotation,
+                        placed.frame,
+                    ],
+                )
+                .context("failed to save igloo furniture")?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn put_login_key(&self, key: &LoginKey) -> Result<()> {
+        let key = key.clone();
+        self.run(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO login_key (penguin_id, key, issued_at, redeemed) \
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![key.penguin_id, key.key, key.issued_at, key.redeemed],
+            )
+            .context("failed to store login key")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn take_login_key(&self, penguin_id: PlayerId, key: &str) -> Result<Option<LoginKey>> {
+        let key = key.to_owned();
+        self.run(move |conn| {
+            conn.query_row(
+                "DELETE FROM login_key WHERE penguin_id = ?1 AND key = ?2 \
+                 RETURNING penguin_id, key, issued_at, redeemed",
+                params![penguin_id, key],
+                |row| {
+                    Ok(LoginKey {
+                        penguin_id: row.get(0)?,
+                        key: row.get(1)?,
+                        issued_at: row.get(2)?,
+                        redeemed: row.get(3)?,
+                    })
+                },
+            )
+            .optional()
+            .context("failed to take login key")
+        })
+        .await
+    }
+
+    async fn has_login_key(&self, penguin_id: PlayerId) -> Result<bool> {
+        self.run(move |conn| {
+            conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM login_key WHERE penguin_id = ?1)",
+                params![penguin_id],
+                |row| row.get(0),
+            )
+            .context("failed to look up login key")
+        })
+        .await
+    }
+
+    async fn prune_login_keys(&self, before: u64) -> Result<()> {
+        self.run(move |conn| {
+            conn.execute(
+                "DELETE FROM login_key WHERE issued_at < ?1",
+                params![before],
+            )
+            .context("failed to prune login keys")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn set_presence(&self, penguin_id: PlayerId, world_id: Option<WorldId>) -> Result<()> {
+        self.run(move |conn| {
+            match world_id {
+                Some(world_id) => conn.execute(
+                    "INSERT OR REPLACE INTO presence (penguin_id, world_id) VALUES (?1, ?2)",
+                    params![penguin_id, world_id],
+                ),
+                None => conn.execute(
+                    "DELETE FROM presence WHERE penguin_id = ?1",
+                    params![penguin_id],
+                ),
+            }
+            .context("failed to set presence")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn clear_presence(&self, world_id: WorldId) -> Result<()> {
+        self.run(move |conn| {
+            conn.execute(
+                "DELETE FROM presence WHERE world_id = ?1",
+                params![world_id],
+            )
+            .context("failed to clear presence")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list_presence(&self, penguin_ids: Vec<PlayerId>) -> Result<Vec<(PlayerId, WorldId)>> {
+        self.run(move |conn| {
+            let mut stmt =
+                conn.prepare_cached("SELECT world_id FROM presence WHERE penguin_id = ?1")?;
+            let mut presence = Vec::new();
+            for id in penguin_ids {
+                if let Some(world_id) = stmt.query_row(params![id], |row| row.get(0)).optional()? {
+                    presence.push((id, world_id));
+                }
+            }
+            Ok(presence)
+        })
+        .await
+    }
+
+    async fn world_populations(&self) -> Result<Vec<(WorldId, usize)>> {
+        self.run(move |conn| {
+            let mut stmt =
+                conn.prepare_cached("SELECT world_id, COUNT(*) FROM presence GROUP BY world_id")?;
+            let populations = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(populations)
+        })
+        .await
+    }
+
+    async fn add_ban(&self, ban: &Ban) -> Result<()> {
+        let ban = ban.clone();
+        self.run(move |conn| {
+            conn.execute(
+                "INSERT INTO ban (penguin_id, issued_at, expires_at, moderator_id, reason) \
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    ban.penguin_id,
+                    ban.issued_at,
+                    ban.expires_at,
+                    ban.moderator_id,
+                    ban.reason,
+                ],
+            )
+            .context("failed to add ban")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn active_ban(&self, penguin_id: PlayerId, now: u64) -> Result<Option<Ban>> {
+        self.run(move |conn| {
+            // NULLs first, a ban for good outlasts everything
+            conn.query_row(
+                "SELECT penguin_id, issued_at, expires_at, moderator_id, reason FROM ban \
+                 WHERE penguin_id = ?1 AND (expires_at IS NULL OR expires_at > ?2) \
+                 ORDER BY expires_at IS NOT NULL, expires_at DESC LIMIT 1",
+                params![penguin_id, now],
+                |row| {
+                    Ok(Ban {
+                        penguin_id: row.get(0)?,
+                        issued_at: row.get(1)?,
+                        expires_at: row.get(2)?,
+                        moderator_id: row.get(3)?,
+                        reason: row.get(4)?,
+                    })
+                },
+            )
+            .optional()
+            .context("failed to query bans")
+        })
+        .await
+    }
+
+    async fn list_buddies(&self, penguin_id: PlayerId) -> Result<Vec<PlayerId>> {
+        self.run(move |conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT buddy_id FROM buddy WHERE penguin_id = ?1 ORDER BY rowid",
+            )?;
+            let buddies = stmt
+                .query_map(params![penguin_id], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<PlayerId>>>()?;
+            Ok(buddies)
+        })
+        .await
+    }
+
+    async fn add_buddy(&self, penguin_id: PlayerId, buddy_id: PlayerId) -> Result<()> {
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            for (a, b) in [(penguin_id, buddy_id), (buddy_id, penguin_id)] {
+                tx.execute(
+                    "INSERT OR IGNORE INTO buddy (penguin_id, buddy_id) VALUES (?1, ?2)",
+                    params![a, b],
+                )
+                .context("failed to add buddy")?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove_buddy(&self, penguin_id: PlayerId, buddy_id: PlayerId) -> Result<()> {
+        self.run(move |conn| {
+            conn.execute(
+                "DELETE FROM buddy WHERE (penguin_id = ?1 AND buddy_id = ?2) \
+                 OR (penguin_id = ?2 AND buddy_id = ?1)",
+                params![penguin_id, buddy_id],
+            )
+            .context("failed to remove buddy")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list_ignored(&self, penguin_id: PlayerId) -> Result<Vec<PlayerId>> {
+        self.run(move |conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT ignore_id FROM ignore WHERE penguin_id = ?1 ORDER BY rowid",
+            )?;
+            let ignored = stmt
+                .query_map(params![penguin_id], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<PlayerId>>>()?;
+            Ok(ignored)
+        })
+        .await
+    }
+
+    async fn add_ignore(&self, penguin_id: PlayerId, ignore_id: PlayerId) -> Result<()> {
+        self.run(move |conn| {
+            conn.execute(
+                "INSERT OR IGNORE INTO ignore (penguin_id, ignore_id) VALUES (?1, ?2)",
+                params![penguin_id, ignore_id],
+            )
+            .context("failed to add ignore")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove_ignore(&self, penguin_id: PlayerId, ignore_id: PlayerId) -> Result<()> {
+        self.run(move |conn| {
+            conn.execute(
+                "DELETE FROM ignore WHERE penguin_id = ?1 AND ignore_id = ?2",
+                params![penguin_id, ignore_id],
+            )
+            .context("failed to remove ignore")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn send_postcard(
+        &self,
+        recipient_id: PlayerId,
+        postcard: &Postcard,
+        mailbox_size: usize,
+    ) -> Result<Option<PostcardId>> {
+        let postcard = postcard.clone();
+        self.run(move |conn| {
+            let sent = conn
+                .execute(
+                    "INSERT INTO postcard \
+                     (recipient_id, sender_id, postcard_type, details, sent_at, has_read) \
+                     SELECT ?1, ?2, ?3, ?4, ?5, ?6 \
+                     WHERE (SELECT COUNT(*) FROM postcard WHERE recipient_id = ?1) < ?7",
+                    params![
+                        recipient_id,
+                        postcard.sender.map(|(id, _)| id),
+                        postcard.postcard_type,
+                        postcard.details,
+                        postcard.sent_at,
+                        postcard.has_read,
+                        mailbox_size,
+                    ],
+                )
+                .context("failed to send postcard")?;
+            Ok((sent != 0).then(|| conn.last_insert_rowid() as PostcardId))
+        })
+        .await
+    }
+
+    async fn list_postcards(&self, recipient_id: PlayerId) -> Result<Vec<Postcard>> {
+        self.run(move |conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT postcard.id, sender_id, nickname, postcard_type, details, sent_at, \
+                 has_read FROM postcard LEFT JOIN penguin ON penguin.id = sender_id \
+                 WHERE recipient_id = ?1 ORDER BY postcard.id DESC",
+            )?;
+            let postcards = stmt
+                .query_map(params![recipient_id], |row| {
+                    let sender_id: Option<PlayerId> = row.get(1)?;
+                    let nickname: Option<String> = row.get(2)?;
+                    Ok(Postcard {
+                        id: row.get(0)?,
+                        sender: sender_id.zip(nickname),
+                        postcard_type: row.get(3)?,
+                        details: row.get(4)?,
+                        sent_at: row.get(5)?,
+                        has_read: row.get(6)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(postcards)
+        })
+        .await
+    }
+
+    async fn read_postcards(&self, recipient_id: PlayerId) -> Result<()> {
+        self.run(move |conn| {
+            conn.execute(
+                "UPDATE postcard SET has_read = 1 WHERE recipient_id = ?1",
+                params![recipient_id],
+            )
+            .context("failed to read postcards")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete_postcard(&self, recipient_id: PlayerId, postcard_id: PostcardId) -> Result<()> {
+        self.run(move |conn| {
+            conn.execute(
+                "DELETE FROM postcard WHERE recipient_id = ?1 AND id = ?2",
+                params![recipient_id, postcard_id],
+            )
+            .context("failed to delete postcard")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete_postcards_from(
+        &self,
+        recipient_id: PlayerId,
+        sender_id: PlayerId,
+    ) -> Result<()> {
+        self.run(move |conn| {
+            conn.execute(
+                "DELETE FROM postcard WHERE recipient_id = ?1 AND sender_id = ?2",
+                params![recipient_id, sender_id],
+            )
+            .context("failed to delete postcards")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn nicknames(&self, penguin_ids: Vec<PlayerId>) -> Result<Vec<(PlayerId, String)>> {
+        self.run(move |conn| {
+            let mut stmt = conn.prepare_cached("SELECT nickname FROM penguin WHERE id = ?1")?;
+            let mut nicknames = Vec::with_capacity(penguin_ids.len());
+            for id in penguin_ids {
+                if let Some(nickname) = stmt.query_row(params![id], |row| row.get(0)).optional()? {
+                    nicknames.push((id, nickname));
+                }
+            }
+            Ok(nicknames)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: &str = r#"{"penguins": [
+        {"id": 102, "username": "kirill", "password_hash": "acbd18db4cc2f85cedef654fccc4a4d8",
+         "nickname": "Kirill", "coins": 100, "color": 1, "head": 429, "inventory": [1, 429],
+         "buddies": [103]},
+        {"id": 103, "username": "basil", "password_hash": "37b51d194a7513e45b56f6524f2d51f2",
+         "nickname": "Basil", "buddies": [102]}
+    ]}"#;
+
+    async fn seeded() -> SqliteManager {
+        let manager = SqliteManager::open_in_memory().expect("failed to open");
+        manager.import(Seed::parse(SEED).unwrap()).await.unwrap();
+        manager
+    }
+
+    #[tokio::test]
+    async fn import_and_load() {
+        let manager = seeded().await;
+        let account = manager.load_account("kirill").await.unwrap().unwrap();
+        assert_eq!(account.id, 102);
+        assert_eq!(account.password_hash, "acbd18db4cc2f85cedef654fccc4a4d8");
+        assert!(manager.load_account("nobody").await.unwrap().is_none());
+
+        let penguin = manager.load_penguin(102).await.unwrap().unwrap();
+        assert_eq!(penguin.coins, 100);
+        assert_eq!(penguin.head, 429);
+        assert_eq!(manager.list_inventory(102).await.unwrap(), vec![1, 429]);
+        assert_eq!(manager.list_buddies(103).await.unwrap(), vec![102]);
+    }
+
+    #[tokio::test]
+    async fn add_inventory_item() {
+        let manager = seeded().await;
+        manager.add_inventory_item(102, 9057).await.unwrap();
+        manager.add_inventory_item(102, 429).await.unwrap();
+        assert_eq!(
+            manager.list_inventory(102).await.unwrap(),
+            vec![1, 429, 9057]
+        );
+        // foreign key
+        assert!(manager.add_inventory_item(999, 1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn save_roundtrip() {
+        let manager = seeded().await;
+        let mut penguin = manager.load_penguin(102).await.unwrap().unwrap();
+        penguin.coins = 42;
+        penguin.minutes_played = 7;
+        penguin.moderator = ModeratorStatus::StealthModerator;
+        penguin.last_login_at = Some(1000);
+        manager.save_penguin(&penguin).await.unwrap();
+        assert_eq!(manager.load_penguin(102).await.unwrap().unwrap(), penguin);
+
+        // importing again must not clobber progress
+        manager.import(Seed::parse(SEED).unwrap()).await.unwrap();
+        assert_eq!(manager.load_penguin(102).await.unwrap().unwrap().coins, 42);
+    }
+
+    #[tokio::test]
+    async fn stamps() {
+        let manager = seeded().await;
+        manager.add_stamp(102, 14).await.unwrap();
+        manager.add_stamp(102, 7).await.unwrap();
+        manager.add_stamp(102, 14).await.unwrap();
+        assert_eq!(
+            manager.list_stamps(102).await.unwrap(),
+            vec![
+                Stamp {
+                    stamp_id: 14,
+                    recent: true
+                },
+                Stamp {
+                    stamp_id: 7,
+                    recent: true
+                }
+            ]
+        );
+        assert!(manager.list_stamps(103).await.unwrap().is_empty());
+        // foreign key
+        assert!(manager.add_stamp(404, 14).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn igloo_round_trip() {
+        let manager = seeded().await;
+        assert_eq!(manager.load_igloo(102).await.unwrap(), None);
+
+        let chair = |x| PlacedFurniture {
+            furniture_id: 305,
+            x,
+            y: 240,
+            rotation: 3,
+            frame: 1,
+        };
+        let mut igloo = Igloo {
+            penguin_id: 102,
+            igloo_type: 2,
+            floor: 4,
+            music: 35,
+            location: 1,
+            locked: false,
+            furniture: vec![chair(100), chair(200)],
+        };
+        manager.save_igloo(&igloo).await.unwrap();
+        assert_eq!(manager.load_igloo(102).await.unwrap(), Some(igloo.clone()));
+
+        // the furniture is replaced, not added to
+        igloo.locked = true;
+        igloo.furniture = vec![chair(-20)];
+        manager.save_igloo(&igloo).await.unwrap();
+        assert_eq!(manager.load_igloo(102).await.unwrap(), Some(igloo.clone()));
+        assert_eq!(manager.load_igloo(103).await.unwrap(), None);
+
+        // foreign key, nothing half done either
+        igloo.penguin_id = 404;
+        assert!(manager.save_igloo(&igloo).await.is_err());
+        assert_eq!(manager.load_igloo(404).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn buddies_both_ways() {
+        let manager = seeded().await;
+        manager.remove_buddy(103, 102).await.unwrap();
+        assert!(manager.list_buddies(102).await.unwrap().is_empty());
+        assert!(manager.list_buddies(103).await.unwrap().is_empty());
+
+        manager.add_buddy(102, 103).await.unwrap();
+        assert_eq!(manager.list_buddies(103).await.unwrap(), vec![102]);
+        assert_eq!(
+            manager.nicknames(vec![102, 404]).await.unwrap(),
+            vec![(102, "Kirill".to_owned())]
+        );
+        // foreign key, nothing half done either
+        assert!(manager.add_buddy(102, 404).await.is_err());
+        assert_eq!(manager.list_buddies(102).await.unwrap(), vec![103]);
+    }
+
+    #[tokio::test]
+    async fn ignores_one_way() {
+        let manager = seeded().await;
+        manager.add_ignore(103, 102).await.unwrap();
+        manager.add_ignore(103, 102).await.unwrap();
+        assert_eq!(manager.list_ignored(103).await.unwrap(), vec![102]);
+        assert!(manager.list_ignored(102).await.unwrap().is_empty());
+
+        manager.remove_ignore(103, 102).await.unwrap();
+        assert!(manager.list_ignored(103).await.unwrap().is_empty());
+        // foreign key
+        assert!(manager.add_ignore(103, 404).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn mailbox() {
+        let manager = seeded().await;
+        let postcard = |sender| Postcard {
+            id: 0,
+            sender,
+            postcard_type: 1,
+            details: "Town".to_owned(),
+            sent_at: 1000,
+            has_read: false,
+        };
+        let from_kirill = manager
+            .send_postcard(103, &postcard(Some((102, String::new()))), 2)
+            .await
+            .unwrap()
+            .unwrap();
+        let from_system = manager
+            .send_postcard(103, &postcard(None), 2)
+            .await
+            .unwrap()
+            .unwrap();
+        // full
+        assert_eq!(
+            manager
+                .send_postcard(103, &postcard(None), 2)
+                .await
+                .unwrap(),
+            None
+        );
+        // foreign key
+        assert!(manager
+            .send_postcard(404, &postcard(None), 2)
+            .await
+            .is_err());
+
+        let mailbox = manager.list_postcards(103).await.unwrap();
+        assert_eq!(
+            mailbox,
+            vec![
+                Postcard {
+                    id: from_system,
+                    ..postcard(None)
+                },
+                Postcard {
+                    id: from_kirill,
+                    ..postcard(Some((102, "Kirill".to_owned())))
+                },
+            ]
+        );
+
+        manager.read_postcards(103).await.unwrap();
+        assert!(manager
+            .list_postcards(103)
+            .await
+            .unwrap()
+            .iter()
+            .all(|p| p.has_read));
+        manager.delete_postcards_from(103, 102).await.unwrap();
+        manager.delete_postcard(102, from_system).await.unwrap();
+        assert_eq!(manager.list_postcards(103).await.unwrap().len(), 1);
+        manager.delete_postcard(103, from_system).await.unwrap();
+        assert!(manager.list_postcards(103).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn bans() {
+        let manager = seeded().await;
+        assert!(manager.active_ban(102, 1000).await.unwrap().is_none());
+
+        let ban = Ban {
+            penguin_id: 102,
+            issued_at: 1000,
+            expires_at: Some(1000 + 24 * 60 * 60),
+            moderator_id: Some(103),
+            reason: "rude".to_owned(),
+        };
+        manager.add_ban(&ban).await.unwrap();
+        assert_eq!(
+            manager.active_ban(102, 2000).await.unwrap(),
+            Some(ban.clone())
+        );
+        assert!(manager
+            .active_ban(102, 1000 + 24 * 60 * 60)
+            .await
+            .unwrap()
+            .is_none());
+
+        let forever = Ban {
+            expires_at: None,
+            ..ban
+        };
+        manager.add_ban(&forever).await.unwrap();
+        assert_eq!(manager.active_ban(102, 2000).await.unwrap(), Some(forever));
+        assert!(manager.active_ban(103, 2000).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn login_keys() {
+        let manager = seeded().await;
+        let key = |penguin_id, issued_at| LoginKey {
+            penguin_id,
+            key: format!("key{penguin_id}"),
+            issued_at,
+            redeemed: false,
+        };
+        manager.put_login_key(&key(102, 1000)).await.unwrap();
+        manager.put_login_key(&key(103, 2000)).await.unwrap();
+        let redeemed = LoginKey {
+            redeemed: true,
+            ..key(102, 1500)
+        };
+        manager.put_login_key(&redeemed).await.unwrap();
+        // foreign key
+        assert!(manager.put_login_key(&key(404, 1000)).await.is_err());
+
+        // the wrong key leaves the right one be
+        assert_eq!(manager.take_login_key(102, "key103").await.unwrap(), None);
+        assert!(manager.has_login_key(102).await.unwrap());
+        assert_eq!(
+            manager.take_login_key(102, "key102").await.unwrap(),
+            Some(redeemed)
+        );
+        assert_eq!(manager.take_login_key(102, "key102").await.unwrap(), None);
+        assert!(!manager.has_login_key(102).await.unwrap());
+        manager.prune_login_keys(2001).await.unwrap();
+        assert!(!manager.has_login_key(103).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn presence() {
+        let manager = seeded().await;
+        manager.set_presence(102, Some(3100)).await.unwrap();
+        manager.set_presence(103, Some(3100)).await.unwrap();
+        manager.set_presence(103, Some(3101)).await.unwrap();
+        // foreign key
+        assert!(manager.set_presence(404, Some(3100)).await.is_err());
+
+        assert_eq!(
+            manager.list_presence(vec![102, 103, 404]).await.unwrap(),
+            vec![(102, 3100), (103, 3101)]
+        );
+        let mut populations = manager.world_populations().await.unwrap();
+        populations.sort();
+        assert_eq!(populations, vec![(3100, 1), (3101, 1)]);
+
+        manager.set_presence(103, None).await.unwrap();
+        manager.clear_presence(3100).await.unwrap();
+        assert!(manager.world_populations().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn migrations_are_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        migrate(&mut conn).unwrap();
+        let version: usize = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+}
@@ -1,12 +1,163 @@
 pub mod manager;
 
-pub struct Player{
-    player_id: usize,
-    name: String,
+pub use manager::{Manager, PersistenceManager};
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+use crate::datamodel::{
+    item::Slot, FloorId, FurnitureId, IglooId, ItemId, LocationId, ModeratorStatus, PlayerId,
+    StampId,
+};
+
+/// Credentials of a penguin, as used by the login handshake
+#[derive(Debug, Clone, PartialEq)]
+pub struct Account {
+    pub id: PlayerId,
+    pub username: String,
+    // md5 hex digest of the password, never the password itself
+    pub password_hash: String,
+}
+
+/// Everything about a penguin that outlives a session
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Penguin {
+    pub id: PlayerId,
+    pub nickname: String,
+    #[serde(default)]
+    pub coins: usize,
+    #[serde(default)]
+    pub safe_chat: bool,
+    #[serde(default)]
+    pub moderator: ModeratorStatus,
+    #[serde(default)]
+    pub minutes_played: usize,
+    // unix timestamp in seconds
+    #[serde(default)]
+    pub registered_at: u64,
+    // same, None if it never joined a world
+    #[serde(default)]
+    pub last_login_at: Option<u64>,
+
+    #[serde(default)]
+    pub color: ItemId,
+    #[serde(default)]
+    pub head: ItemId,
+    #[serde(default)]
+    pub face: ItemId,
+    #[serde(default)]
+    pub neck: ItemId,
+    #[serde(default)]
+    pub body: ItemId,
+    #[serde(default)]
+    pub hand: ItemId,
+    #[serde(default)]
+    pub feet: ItemId,
+    #[serde(default)]
+    pub flag: ItemId,
+    #[serde(default)]
+    pub photo: ItemId,
+}
+
+/// Unix timestamp in seconds, what every `*_at` field is stored as
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time not available?")
+        .as_secs()
 }
 
+/// A stamp in a penguin's stamp book
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stamp {
+    pub stamp_id: StampId,
+    // earned since the penguin last opened the book
+    pub recent: bool,
+}
 
+/// A penguin's igloo, as it was last decorated
+#[derive(Debug, Clone, PartialEq)]
+pub struct Igloo {
+    pub penguin_id: PlayerId,
+    pub igloo_type: IglooId,
+    pub floor: FloorId,
+    // 0 for silence
+    pub music: usize,
+    pub location: LocationId,
+    // closed to visitors
+    pub locked: bool,
+    pub furniture: Vec<PlacedFurniture>,
+}
 
+/// One piece of furniture standing in an igloo
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedFurniture {
+    pub furniture_id: FurnitureId,
+    pub x: isize,
+    pub y: isize,
+    pub rotation: u8,
+    pub frame: u8,
+}
 
+/// Handed out by the login server, presented to a world twice, see `conn::login_key`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoginKey {
+    pub penguin_id: PlayerId,
+    pub key: String,
+    // unix timestamp in seconds of the last step it went through
+    pub issued_at: u64,
+    // taken by the handshake of a world, only JoinServer is left
+    pub redeemed: bool,
+}
 
+/// Keeps a penguin out of every world until it runs out
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ban {
+    pub penguin_id: PlayerId,
+    // unix timestamps in seconds
+    pub issued_at: u64,
+    // None for good
+    pub expires_at: Option<u64>,
+    // None for automatic bans
+    pub moderator_id: Option<PlayerId>,
+    pub reason: String,
+}
 
+impl Ban {
+    /// Whole hours left at `now`, None if the ban never runs out
+    pub fn hours_left(&self, now: u64) -> Option<u64> {
+        self.expires_at
+            .map(|expires_at| expires_at.saturating_sub(now) / (60 * 60))
+    }
+}
+
+impl Penguin {
+    pub fn slot(&self, slot: Slot) -> ItemId {
+        match slot {
+            Slot::Color => self.color,
+            Slot::Head => self.head,
+            Slot::Face => self.face,
+            Slot::Neck => self.neck,
+            Slot::Body => self.body,
+            Slot::Hand => self.hand,
+            Slot::Feet => self.feet,
+            Slot::Flag => self.flag,
+            Slot::Photo => self.photo,
+        }
+    }
+
+    pub fn slot_mut(&mut self, slot: Slot) -> &mut ItemId {
+        match slot {
+            Slot::Color => &mut self.color,
+            Slot::Head => &mut self.head,
+            Slot::Face => &mut self.face,
+            Slot::Neck => &mut self.neck,
+            Slot::Body => &mut self.body,
+            Slot::Hand => &mut self.hand,
+            Slot::Feet => &mut self.feet,
+            Slot::Flag => &mut self.flag,
+            Slot::Photo => &mut self.photo,
+        }
+    }
+}
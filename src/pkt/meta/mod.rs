@@ -1,13 +1,7 @@
 pub type PlayerId = usize;
 pub type RoomId = usize;
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum ModeratorStatus {
-    Mascot,
-    StealthModerator,
-    Moderator,
-    None, //lol
-}
+pub use crate::datamodel::ModeratorStatus;
 pub mod client {
     use crate::{datamodel, pkt::meta::PlayerId};
 
@@ -43,24 +37,126 @@ pub mod client {
         },
         SendMessage{
             message: String,
-        }
+        },
+        JoinRoom {
+            room_id: datamodel::RoomId,
+            x: isize,
+            y: isize,
+        },
+        UpdateOutfit {
+            slot: datamodel::item::Slot,
+            item_id: datamodel::ItemId,
+        },
+        AddItem {
+            item_id: datamodel::ItemId,
+        },
+        SendEmote {
+            emote_id: usize,
+        },
+        SendFrame {
+            frame: u8,
+        },
+        SendAction {
+            action_id: u8,
+        },
+        ThrowSnowball {
+            x: isize,
+            y: isize,
+        },
+        SendJoke {
+            joke_id: usize,
+        },
+        // pre-built phrases, the only way safe chat penguins get to talk
+        SendSafeMessage {
+            message_id: usize,
+        },
+        SendLineMessage {
+            line_id: usize,
+        },
+        SendTourMessage {
+            message_id: usize,
+        },
+        SendGuideMessage {
+            message_id: usize,
+        },
+        Kick {
+            player_id: PlayerId,
+        },
+        // toggles, muted players talk to nobody until unmuted
+        Mute {
+            player_id: PlayerId,
+        },
+        Ban {
+            player_id: PlayerId,
+            // `None` is `moderation.ban_hours`, 0 is forever
+            hours: Option<u64>,
+            message: String,
+        },
+        // only ever typed as `!warn`, the client has no packet for it
+        Warn {
+            player_id: PlayerId,
+            message: String,
+        },
+        // same, `!party <name>`
+        StartParty {
+            name: String,
+        },
+        BuddyRequest {
+            player_id: PlayerId,
+        },
+        BuddyAccept {
+            player_id: PlayerId,
+        },
+        BuddyReject {
+            player_id: PlayerId,
+        },
+        BuddyRemove {
+            player_id: PlayerId,
+        },
+        // where is this buddy right now
+        FindBuddy {
+            player_id: PlayerId,
+        },
+        AddIgnore {
+            player_id: PlayerId,
+        },
+        RemoveIgnore {
+            player_id: PlayerId,
+        },
+        SendPostcard {
+            recipient_id: PlayerId,
+            postcard_type: usize,
+        },
+        // the mailbox was opened, everything in it counts as read
+        ReadPostcards,
+        DeletePostcard {
+            postcard_id: datamodel::PostcardId,
+        },
+        DeletePostcardsFrom {
+            sender_id: PlayerId,
+        },
     }
 }
 
 pub mod server {
-    use std::usize;
-
     use crate::{datamodel, pkt::meta::ModeratorStatus};
 
     #[derive(Clone, Debug, PartialEq)]
     pub enum Packet {
         Heartbeat,
         Error(Error),
+        // BanForever, BanAnHour or BanDuration depending on what is left
+        Banned {
+            hours_left: Option<u64>,
+        },
         Loaded,
         LoginResponse {
-            // hash: String,
-            // // TODO: why does it have a pipe?
-            // buddies_online: String,
+            player_id: datamodel::PlayerId,
+            login_key: String,
+            // worlds at least one buddy is currently on
+            buddy_worlds: Vec<datamodel::WorldId>,
+            // world id and population bucket, 0 (empty) to 7 (full)
+            populations: Vec<(datamodel::WorldId, usize)>,
         },
         ActiveFeatures {
             // TODO
@@ -92,11 +188,36 @@ pub mod server {
         GetInventory {
             items: Vec<datamodel::ItemId>,
         },
-        // TODO:
         GetBuddies {
-            // buddies: Vec<datamodel::Buddy>,
+            buddies: Vec<datamodel::Buddy>,
+        },
+        // someone would like to be buddies
+        BuddyRequest {
+            player_id: datamodel::PlayerId,
+            nickname: String,
+        },
+        BuddyAccepted {
+            player_id: datamodel::PlayerId,
+            nickname: String,
+        },
+        BuddyRemoved {
+            player_id: datamodel::PlayerId,
+            nickname: String,
+        },
+        BuddyOnline {
+            player_id: datamodel::PlayerId,
+        },
+        BuddyOffline {
+            player_id: datamodel::PlayerId,
+        },
+        // None if the buddy is offline or in no room at all
+        BuddyFound {
+            room_id: Option<datamodel::RoomId>,
+        },
+        GetIgnoreList {
+            // id and nickname
+            ignored: Vec<(datamodel::PlayerId, String)>,
         },
-        GetIgnoreList {},
         //TODO
         GetPlayerStamps {
             player_id: datamodel::PlayerId,
@@ -105,8 +226,18 @@ pub mod server {
         QueryPlayerAwards {
             player_id: datamodel::PlayerId,
         },
-        // TODO
-        GetMail {},
+        GetMail {
+            postcards: Vec<datamodel::Postcard>,
+        },
+        // answer to the sender, coins left after paying
+        PostcardSent {
+            coins: usize,
+            status: PostcardStatus,
+        },
+        // arrived while the recipient is online
+        ReceivePostcard {
+            postcard: datamodel::Postcard,
+        },
         GetLastRevision(String),
         StartMailEngine {
             unread_mail_count: usize,
@@ -127,6 +258,19 @@ pub mod server {
         AddedPlayer {
             player: datamodel::PlayerGist,
         },
+        RemovePlayer {
+            player_id: datamodel::PlayerId,
+        },
+        UpdatedOutfit {
+            player_id: datamodel::PlayerId,
+            slot: datamodel::item::Slot,
+            item_id: datamodel::ItemId,
+        },
+        AddItem {
+            item_id: datamodel::ItemId,
+            // balance after paying for it
+            coins: usize,
+        },
         // TODO:
         GetWaddlePopulation {},
         GetPlayer {
@@ -140,7 +284,54 @@ pub mod server {
         SendMessage{
             player_id: datamodel::PlayerId,
             message: String,
-        }
+        },
+        SendEmote {
+            player_id: datamodel::PlayerId,
+            emote_id: usize,
+        },
+        SendFrame {
+            player_id: datamodel::PlayerId,
+            frame: u8,
+        },
+        SendAction {
+            player_id: datamodel::PlayerId,
+            action_id: u8,
+        },
+        ThrowSnowball {
+            player_id: datamodel::PlayerId,
+            x: isize,
+            y: isize,
+        },
+        SendJoke {
+            player_id: datamodel::PlayerId,
+            joke_id: usize,
+        },
+        SendSafeMessage {
+            player_id: datamodel::PlayerId,
+            message_id: usize,
+        },
+        SendLineMessage {
+            player_id: datamodel::PlayerId,
+            line_id: usize,
+        },
+        SendTourMessage {
+            player_id: datamodel::PlayerId,
+            message_id: usize,
+        },
+        SendGuideMessage {
+            player_id: datamodel::PlayerId,
+            message_id: usize,
+        },
+    }
+
+    #[repr(u32)]
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum PostcardStatus {
+        MailboxFull = 0,
+        Sent = 1,
+        NotEnoughCoins = 2,
+        // ours, the stock client has no words for it
+        NoSuchPenguin = 3,
     }
 
     #[repr(u32)]
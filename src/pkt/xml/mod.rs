@@ -1,5 +1,5 @@
 pub mod client {
-    use anyhow::{anyhow, Result};
+    use anyhow::Result;
     use quick_xml::{events::Event, Reader};
 
     #[derive(Debug, Clone, PartialEq)]
@@ -8,6 +8,8 @@ pub mod client {
         // TODO: not sure whether fields relevant!
         RandomKey,
         Login { username: String, password: String },
+        // flash asks before connecting: <policy-file-request/>
+        PolicyFileRequest,
     }
 
     // TODO: having a fullfletched xml parser as a
@@ -20,7 +22,9 @@ pub mod client {
         let mut username = String::new();
         let mut password = String::new();
         let mut in_nick = false;
-        let mut in_pword = false; loop {
+        let mut in_pword = false;
+        let mut policy_request = false;
+        loop {
             match reader.read_event_into(&mut buf)? {
                 Event::Start(e) => match e.name().as_ref() {
                     b"body" => {
@@ -35,16 +39,17 @@ pub mod client {
                     b"pword" => in_pword = true,
                     _ => {}
                 },
-                Event::Empty(e) => {
-                    if e.name().as_ref() == b"ver" {
-                        for attr in e.attributes() {
-                            let attr = attr?;
-                            if attr.key.as_ref() == b"v" {
-                                version = std::str::from_utf8(&attr.value)?.to_string();
-                            }
+                Event::Empty(e) if e.name().as_ref() == b"ver" => {
+                    for attr in e.attributes() {
+                        let attr = attr?;
+                        if attr.key.as_ref() == b"v" {
+                            version = std::str::from_utf8(&attr.value)?.to_string();
                         }
                     }
                 }
+                Event::Empty(e) if e.name().as_ref() == b"policy-file-request" => {
+                    policy_request = true;
+                }
                 Event::End(e) => match e.name().as_ref() {
                     b"nick" => in_nick = false,
                     b"pword" => in_pword = false,
@@ -64,6 +69,10 @@ pub mod client {
             buf.clear();
         }
 
+        if policy_request {
+            return Ok(Packet::PolicyFileRequest);
+        }
+
         match action.as_str() {
             "verChk" => Ok(Packet::VersionCheck { expected: version }),
             "rndK" => Ok(Packet::RandomKey),
@@ -83,14 +92,14 @@ pub mod client {
 }
 
 pub mod server {
-    use anyhow::Result;
-
     #[derive(Debug, Clone, PartialEq)]
     pub enum Packet {
         //<msg t="sys"><body action="apiOK" r="0" /></msg>
         ApiOK,
         //<msg t="sys"><body action="rndK" r="-1"><k>houdini</k></body></msg>
         RandomKey(String),
+        //<cross-domain-policy><allow-access-from domain="*" to-ports="6969,1337" /></cross-domain-policy>
+        PolicyFile { domains: Vec<String>, ports: Vec<u16> },
     }
 
     // super lazy lmaooooo
@@ -103,12 +112,26 @@ pub mod server {
                     key
                 )
             }
+            Packet::PolicyFile { domains, ports } => {
+                let ports = ports
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                let allowed: String = domains
+                    .iter()
+                    .map(|domain| {
+                        format!(r#"<allow-access-from domain="{domain}" to-ports="{ports}" />"#)
+                    })
+                    .collect();
+                format!("<cross-domain-policy>{allowed}</cross-domain-policy>")
+            }
         }
     }
 
-    impl Into<String> for Packet{
-        fn into(self) -> String {
-            serialize(self)
+    impl From<Packet> for String{
+        fn from(val: Packet) -> Self {
+            serialize(val)
         }
 
     }
@@ -124,6 +147,13 @@ mod tests {
     fn basic_serialization() {
         assert_eq!(server::serialize(server::Packet::ApiOK), r#"<msg t="sys"><body action="apiOK" r="0" /></msg>"#);
         assert_eq!(server::serialize(server::Packet::RandomKey("foo".to_owned())), r#"<msg t="sys"><body action="rndK" r="-1"><k>foo</k></body></msg>"#);
+        assert_eq!(
+            server::serialize(server::Packet::PolicyFile {
+                domains: vec!["*".to_owned()],
+                ports: vec![6969, 1337]
+            }),
+            r#"<cross-domain-policy><allow-access-from domain="*" to-ports="6969,1337" /></cross-domain-policy>"#
+        );
     }
 
     #[test]
@@ -149,5 +179,11 @@ mod tests {
             if username == "kirill" && password == "foo"
             )
         }
+
+        {
+            let raw = "<policy-file-request/>";
+            let pkt: client::Packet = client::deserialize(raw).expect("failed to deserialize");
+            assert_matches!(pkt, client::Packet::PolicyFileRequest)
+        }
     }
 }
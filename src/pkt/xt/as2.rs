@@ -1,11 +1,71 @@
+use crate::{
+    datamodel::{item::Slot, Postcard},
+    pkt::{
+        meta,
+        xt::{Protocol, XTPacket},
+    },
+};
+
+pub struct As2;
+
+// the same ids are used for requests (prefixed with "s#") and responses
+const OUTFIT_PACKETS: [(Slot, &str); 9] = [
+    (Slot::Color, "upc"),
+    (Slot::Head, "uph"),
+    (Slot::Face, "upf"),
+    (Slot::Neck, "upn"),
+    (Slot::Body, "upb"),
+    (Slot::Hand, "upa"),
+    (Slot::Feet, "upe"),
+    (Slot::Flag, "upl"),
+    (Slot::Photo, "upp"),
+];
+
+fn outfit_slot(packet_id: &str) -> Option<Slot> {
+    OUTFIT_PACKETS
+        .iter()
+        .find(|(_, id)| *id == packet_id)
+        .map(|(slot, _)| *slot)
+}
+
+fn outfit_packet_id(slot: Slot) -> &'static str {
+    OUTFIT_PACKETS
+        .iter()
+        .find(|(s, _)| *s == slot)
+        .map(|(_, id)| *id)
+        .expect("every slot has a packet")
+}
+
+/// Sender nickname and id, type, details, timestamp and id, the system signs as "sys" 0
+fn postcard_fields(postcard: &Postcard) -> [String; 6] {
+    let (sender_id, nickname) = postcard.sender.clone().unwrap_or((0, "sys".to_owned()));
+    [
+        nickname,
+        sender_id.to_string(),
+        postcard.postcard_type.to_string(),
+        postcard.details.clone(),
+        postcard.sent_at.to_string(),
+        postcard.id.to_string(),
+    ]
+}
+
+impl Protocol for As2 {
+    const NAME: &'static str = "as2";
+
+    fn decode(xt: XTPacket) -> Result<meta::client::Packet, client::PacketError> {
+        client::Packet::try_from(xt).map(|packet| packet.0)
+    }
+
+    fn encode(packet: meta::server::Packet) -> XTPacket {
+        server::Packet(packet).into()
+    }
+}
+
 pub mod client {
-    use crate::{
-        datamodel::{self, IntoPlayerGistString},
-        pkt::{self, meta},
-    };
+    use super::outfit_slot;
+    use crate::pkt::{self, meta};
     use std::num::ParseIntError;
 
-    use anyhow::Context;
     use thiserror::Error;
 
     use crate::pkt::xt::XTPacket;
@@ -69,6 +129,18 @@ pub mod client {
                     [] => Ok(meta::client::Packet::GetIgnoreList),
                     _ => Err(PacketError::BadArgCount),
                 },
+                ("s", "n#an") => match data {
+                    [player_id] => Ok(meta::client::Packet::AddIgnore {
+                        player_id: player_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "n#rn") => match data {
+                    [player_id] => Ok(meta::client::Packet::RemoveIgnore {
+                        player_id: player_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
                 ("s", "l#mst") => match data {
                     [] => Ok(meta::client::Packet::StartMailEngine),
                     _ => Err(PacketError::BadArgCount),
@@ -77,6 +149,29 @@ pub mod client {
                     [] => Ok(meta::client::Packet::GetMail),
                     _ => Err(PacketError::BadArgCount),
                 },
+                ("s", "l#ms") => match data {
+                    [recipient_id, postcard_type] => Ok(meta::client::Packet::SendPostcard {
+                        recipient_id: recipient_id.parse()?,
+                        postcard_type: postcard_type.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "l#mc") => match data {
+                    [] => Ok(meta::client::Packet::ReadPostcards),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "l#md") => match data {
+                    [postcard_id] => Ok(meta::client::Packet::DeletePostcard {
+                        postcard_id: postcard_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "l#mdp") => match data {
+                    [sender_id] => Ok(meta::client::Packet::DeletePostcardsFrom {
+                        sender_id: sender_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
                 ("s", "p#pgu") => match data {
                     [] => Ok(meta::client::Packet::GetMyPuffles),
                     _ => Err(PacketError::BadArgCount),
@@ -104,9 +199,7 @@ pub mod client {
                     }),
                     _ => Err(PacketError::BadArgCount),
                 },
-                ("z", "gw") => match data {
-                    _ => Ok(meta::client::Packet::GetWaddlePopulation {}),
-                },
+                ("z", "gw") => Ok(meta::client::Packet::GetWaddlePopulation {}),
                 ("s", "u#gp") => match data {
                     [player_id] => Ok(meta::client::Packet::GetPlayer {
                         player: player_id.parse()?,
@@ -120,6 +213,31 @@ pub mod client {
                     }),
                     _ => Err(PacketError::BadArgCount),
                 },
+                ("s", "j#jr") => match data {
+                    [room_id, x, y] => Ok(meta::client::Packet::JoinRoom {
+                        room_id: room_id.parse()?,
+                        x: x.parse()?,
+                        y: y.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                (
+                    "s",
+                    "s#upc" | "s#uph" | "s#upf" | "s#upn" | "s#upb" | "s#upa" | "s#upe" | "s#upl"
+                    | "s#upp",
+                ) => match data {
+                    [item_id] => Ok(meta::client::Packet::UpdateOutfit {
+                        slot: outfit_slot(&packet_id[2..]).expect("matched above"),
+                        item_id: item_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "i#ai") => match data {
+                    [item_id] => Ok(meta::client::Packet::AddItem {
+                        item_id: item_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
                 ("s", "m#sm") => match data {
                     // cp sends the penguin id alongside ... not sure why
                     // we discard it, proper error handling could be nice ... but eh
@@ -128,6 +246,116 @@ pub mod client {
                     }),
                     _ => Err(PacketError::BadArgCount),
                 },
+                ("s", "u#se") => match data {
+                    [emote_id] => Ok(meta::client::Packet::SendEmote {
+                        emote_id: emote_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "u#sf") => match data {
+                    [frame] => Ok(meta::client::Packet::SendFrame {
+                        frame: frame.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "u#sa") => match data {
+                    [action_id] => Ok(meta::client::Packet::SendAction {
+                        action_id: action_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "u#sb") => match data {
+                    [x, y] => Ok(meta::client::Packet::ThrowSnowball {
+                        x: x.parse()?,
+                        y: y.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "u#sj") => match data {
+                    [joke_id] => Ok(meta::client::Packet::SendJoke {
+                        joke_id: joke_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "u#ss") => match data {
+                    [message_id] => Ok(meta::client::Packet::SendSafeMessage {
+                        message_id: message_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "u#sl") => match data {
+                    [line_id] => Ok(meta::client::Packet::SendLineMessage {
+                        line_id: line_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "u#st") => match data {
+                    [message_id] => Ok(meta::client::Packet::SendTourMessage {
+                        message_id: message_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "u#sg") => match data {
+                    [message_id] => Ok(meta::client::Packet::SendGuideMessage {
+                        message_id: message_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "o#k") => match data {
+                    [player_id] => Ok(meta::client::Packet::Kick {
+                        player_id: player_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "o#m") => match data {
+                    [player_id] => Ok(meta::client::Packet::Mute {
+                        player_id: player_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "o#b") => match data {
+                    [player_id, message] => Ok(meta::client::Packet::Ban {
+                        player_id: player_id.parse()?,
+                        hours: None,
+                        message: message.to_owned(),
+                    }),
+                    [player_id, message, hours] => Ok(meta::client::Packet::Ban {
+                        player_id: player_id.parse()?,
+                        hours: Some(hours.parse()?),
+                        message: message.to_owned(),
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "b#br") => match data {
+                    [player_id] => Ok(meta::client::Packet::BuddyRequest {
+                        player_id: player_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "b#ba") => match data {
+                    [player_id] => Ok(meta::client::Packet::BuddyAccept {
+                        player_id: player_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "b#rj") => match data {
+                    [player_id] => Ok(meta::client::Packet::BuddyReject {
+                        player_id: player_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "b#rb") => match data {
+                    [player_id] => Ok(meta::client::Packet::BuddyRemove {
+                        player_id: player_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
+                ("s", "u#bf") => match data {
+                    [player_id] => Ok(meta::client::Packet::FindBuddy {
+                        player_id: player_id.parse()?,
+                    }),
+                    _ => Err(PacketError::BadArgCount),
+                },
                 _ => Err(PacketError::Unrecognized {
                     handler_id: handler_id.to_owned(),
                     packet_id: packet_id.to_owned(),
@@ -139,11 +367,11 @@ pub mod client {
 }
 
 pub mod server {
+    use super::{outfit_packet_id, postcard_fields};
     use crate::{
         datamodel::{self, IntoPlayerGistString},
         pkt::{
             self,
-            meta::ModeratorStatus,
             xt::{XTPacket, XT_DEFAULT_INT_ID},
         },
     };
@@ -152,17 +380,33 @@ pub mod server {
     #[derive(Clone, Debug, PartialEq)]
     pub struct Packet(pub pkt::meta::server::Packet);
 
-    impl Into<String> for Packet {
-        fn into(self) -> String {
-            let xt: XTPacket = self.into();
+    impl From<Packet> for String {
+        fn from(val: Packet) -> Self {
+            let xt: XTPacket = val.into();
             xt.into()
         }
     }
 
-    impl Into<XTPacket> for Packet {
-        fn into(self) -> XTPacket {
-            match self.0 {
+    impl From<Packet> for XTPacket {
+        fn from(val: Packet) -> Self {
+            match val.0 {
                 pkt::meta::server::Packet::Heartbeat => todo!(),
+                pkt::meta::server::Packet::Banned { hours_left } => {
+                    let data = match hours_left {
+                        None => vec![(pkt::meta::server::Error::BanForever as u32).to_string()],
+                        Some(0) => vec![(pkt::meta::server::Error::BanAnHour as u32).to_string()],
+                        Some(hours) => vec![
+                            (pkt::meta::server::Error::BanDuration as u32).to_string(),
+                            hours.to_string(),
+                        ],
+                    };
+                    XTPacket {
+                        handler_id: None,
+                        packet_id: "e".to_owned(),
+                        internal_id: XT_DEFAULT_INT_ID,
+                        data,
+                    }
+                }
                 pkt::meta::server::Packet::Error(error) => {
                     let error: u32 = error.clone() as u32;
                     XTPacket {
@@ -178,12 +422,29 @@ pub mod server {
                     internal_id: XT_DEFAULT_INT_ID,
                     data: vec![],
                 },
-                pkt::meta::server::Packet::LoginResponse {} => XTPacket {
+                pkt::meta::server::Packet::LoginResponse {
+                    player_id,
+                    login_key,
+                    buddy_worlds,
+                    populations,
+                } => XTPacket {
                     handler_id: None,
                     packet_id: "l".to_owned(),
                     internal_id: XT_DEFAULT_INT_ID,
-                    // TODO: why does houdini return empty?
-                    data: vec!["".to_owned()],
+                    data: vec![
+                        player_id.to_string(),
+                        login_key,
+                        buddy_worlds
+                            .iter()
+                            .map(|w| w.to_string())
+                            .collect::<Vec<_>>()
+                            .join("|"),
+                        populations
+                            .iter()
+                            .map(|(w, bucket)| format!("{w},{bucket}"))
+                            .collect::<Vec<_>>()
+                            .join("|"),
+                    ],
                 },
                 pkt::meta::server::Packet::ActiveFeatures {} => XTPacket {
                     handler_id: None,
@@ -206,13 +467,7 @@ pub mod server {
                             "0".to_owned()
                         },
                         "0".to_owned(), // TODO: wtf is this? beta?
-                        match moderator_status {
-                            ModeratorStatus::Mascot => 3,
-                            ModeratorStatus::StealthModerator => 2,
-                            ModeratorStatus::Moderator => 1,
-                            ModeratorStatus::None => 0,
-                        }
-                        .to_string(),
+                        moderator_status.code().to_string(),
                         if book_modified {
                             "1".to_owned()
                         } else {
@@ -232,7 +487,7 @@ pub mod server {
                     server_time_offset,
                     opened_playercard,
                     map_category,
-                    new_player_status,
+                    new_player_status: _,
                 } => XTPacket {
                     handler_id: None,
                     packet_id: "lp".to_owned(),
@@ -260,9 +515,7 @@ pub mod server {
                         match map_category {
                             datamodel::MapCategory::Normal => "0".to_owned(),
                         },
-                        match new_player_status {
-                            _ => "0".to_owned(),
-                        },
+                        "0".to_owned(),
                     ],
                 },
                 pkt::meta::server::Packet::GetInventory { items } => XTPacket {
@@ -271,17 +524,69 @@ pub mod server {
                     internal_id: XT_DEFAULT_INT_ID,
                     data: items.into_iter().map(|i| i.to_string()).collect(),
                 },
-                pkt::meta::server::Packet::GetBuddies {} => XTPacket {
+                pkt::meta::server::Packet::GetBuddies { buddies } => XTPacket {
                     handler_id: None,
                     packet_id: "gb".to_owned(),
                     internal_id: XT_DEFAULT_INT_ID,
-                    data: vec![],
+                    data: buddies
+                        .into_iter()
+                        .map(|b| format!("{}|{}|{}", b.id, b.nickname, b.online as u8))
+                        .collect(),
+                },
+                pkt::meta::server::Packet::BuddyRequest {
+                    player_id,
+                    nickname,
+                } => XTPacket {
+                    handler_id: None,
+                    packet_id: "br".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string(), nickname],
                 },
-                pkt::meta::server::Packet::GetIgnoreList {} => XTPacket {
+                pkt::meta::server::Packet::BuddyAccepted {
+                    player_id,
+                    nickname,
+                } => XTPacket {
+                    handler_id: None,
+                    packet_id: "ba".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string(), nickname],
+                },
+                pkt::meta::server::Packet::BuddyRemoved {
+                    player_id,
+                    nickname,
+                } => XTPacket {
+                    handler_id: None,
+                    packet_id: "rb".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string(), nickname],
+                },
+                pkt::meta::server::Packet::BuddyOnline { player_id } => XTPacket {
+                    handler_id: None,
+                    packet_id: "bon".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string()],
+                },
+                pkt::meta::server::Packet::BuddyOffline { player_id } => XTPacket {
+                    handler_id: None,
+                    packet_id: "bof".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string()],
+                },
+                // the client reads -1 as offline
+                pkt::meta::server::Packet::BuddyFound { room_id } => XTPacket {
+                    handler_id: None,
+                    packet_id: "bf".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![room_id.map_or("-1".to_owned(), |id| id.to_string())],
+                },
+                pkt::meta::server::Packet::GetIgnoreList { ignored } => XTPacket {
                     handler_id: None,
                     packet_id: "gn".to_owned(),
                     internal_id: XT_DEFAULT_INT_ID,
-                    data: vec![],
+                    data: ignored
+                        .into_iter()
+                        .map(|(id, nickname)| format!("{id}|{nickname}"))
+                        .collect(),
                 },
                 pkt::meta::server::Packet::GetPlayerStamps { player_id } => XTPacket {
                     handler_id: None,
@@ -297,14 +602,33 @@ pub mod server {
                     data: vec![player_id.to_string(), "8009".to_owned()],
                 },
 
-                pkt::meta::server::Packet::GetMail {} => XTPacket {
+                pkt::meta::server::Packet::GetMail { postcards } => XTPacket {
                     handler_id: None,
                     packet_id: "mg".to_owned(),
                     internal_id: XT_DEFAULT_INT_ID,
-                    data: vec![
-                        "sys|0|112||1752891915|2|1".to_owned(),
-                        "sys|0|125||1752251683|1|1".to_owned(),
-                    ],
+                    data: postcards
+                        .into_iter()
+                        .map(|postcard| {
+                            let [nickname, sender_id, postcard_type, details, sent_at, id] =
+                                postcard_fields(&postcard);
+                            format!(
+                                "{nickname}|{sender_id}|{postcard_type}|{details}|{sent_at}|{id}|{}",
+                                postcard.has_read as u8
+                            )
+                        })
+                        .collect(),
+                },
+                pkt::meta::server::Packet::PostcardSent { coins, status } => XTPacket {
+                    handler_id: None,
+                    packet_id: "ms".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![coins.to_string(), (status as u32).to_string()],
+                },
+                pkt::meta::server::Packet::ReceivePostcard { postcard } => XTPacket {
+                    handler_id: None,
+                    packet_id: "mr".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: postcard_fields(&postcard).into(),
                 },
                 pkt::meta::server::Packet::GetLastRevision(revision) => XTPacket {
                     handler_id: None,
@@ -365,6 +689,28 @@ pub mod server {
                     internal_id: XT_DEFAULT_INT_ID,
                     data: vec![player.into_gist_string()],
                 },
+                pkt::meta::server::Packet::RemovePlayer { player_id } => XTPacket {
+                    handler_id: None,
+                    packet_id: "rp".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string()],
+                },
+                pkt::meta::server::Packet::UpdatedOutfit {
+                    player_id,
+                    slot,
+                    item_id,
+                } => XTPacket {
+                    handler_id: None,
+                    packet_id: outfit_packet_id(slot).to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string(), item_id.to_string()],
+                },
+                pkt::meta::server::Packet::AddItem { item_id, coins } => XTPacket {
+                    handler_id: None,
+                    packet_id: "ai".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![item_id.to_string(), coins.to_string()],
+                },
                 pkt::meta::server::Packet::GetWaddlePopulation {} => XTPacket {
                     handler_id: None,
                     packet_id: "gw".to_owned(),
@@ -390,6 +736,75 @@ pub mod server {
                     internal_id: XT_DEFAULT_INT_ID,
                     data: vec![player_id.to_string(), message],
                 },
+                pkt::meta::server::Packet::SendEmote {
+                    player_id,
+                    emote_id,
+                } => XTPacket {
+                    handler_id: None,
+                    packet_id: "se".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string(), emote_id.to_string()],
+                },
+                pkt::meta::server::Packet::SendFrame { player_id, frame } => XTPacket {
+                    handler_id: None,
+                    packet_id: "sf".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string(), frame.to_string()],
+                },
+                pkt::meta::server::Packet::SendAction {
+                    player_id,
+                    action_id,
+                } => XTPacket {
+                    handler_id: None,
+                    packet_id: "sa".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string(), action_id.to_string()],
+                },
+                pkt::meta::server::Packet::ThrowSnowball { player_id, x, y } => XTPacket {
+                    handler_id: None,
+                    packet_id: "sb".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string(), x.to_string(), y.to_string()],
+                },
+                pkt::meta::server::Packet::SendJoke { player_id, joke_id } => XTPacket {
+                    handler_id: None,
+                    packet_id: "sj".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string(), joke_id.to_string()],
+                },
+                pkt::meta::server::Packet::SendSafeMessage {
+                    player_id,
+                    message_id,
+                } => XTPacket {
+                    handler_id: None,
+                    packet_id: "ss".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string(), message_id.to_string()],
+                },
+                pkt::meta::server::Packet::SendLineMessage { player_id, line_id } => XTPacket {
+                    handler_id: None,
+                    packet_id: "sl".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string(), line_id.to_string()],
+                },
+                pkt::meta::server::Packet::SendTourMessage {
+                    player_id,
+                    message_id,
+                } => XTPacket {
+                    handler_id: None,
+                    packet_id: "st".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string(), message_id.to_string()],
+                },
+                pkt::meta::server::Packet::SendGuideMessage {
+                    player_id,
+                    message_id,
+                } => XTPacket {
+                    handler_id: None,
+                    packet_id: "sg".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![player_id.to_string(), message_id.to_string()],
+                },
             }
         }
     }
@@ -445,3 +860,191 @@ pub mod server {
 //         assert_matches!(res, Err(client::PacketError::BadDatatypeInt(_)))
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outfit_packets() {
+        let xt = XTPacket {
+            handler_id: Some("s".to_owned()),
+            packet_id: "s#uph".to_owned(),
+            internal_id: -1,
+            data: vec!["429".to_owned()],
+        };
+        assert_eq!(
+            As2::decode(xt),
+            Ok(meta::client::Packet::UpdateOutfit {
+                slot: Slot::Head,
+                item_id: 429
+            })
+        );
+
+        let raw: String = server::Packet(meta::server::Packet::UpdatedOutfit {
+            player_id: 102,
+            slot: Slot::Photo,
+            item_id: 9057,
+        })
+        .into();
+        assert_eq!(raw, "%xt%upp%-1%102%9057%");
+
+        for slot in Slot::ALL {
+            assert_eq!(outfit_slot(outfit_packet_id(slot)), Some(slot));
+        }
+    }
+    #[test]
+    fn add_item() {
+        let xt = XTPacket {
+            handler_id: Some("s".to_owned()),
+            packet_id: "i#ai".to_owned(),
+            internal_id: -1,
+            data: vec!["413".to_owned()],
+        };
+        assert_eq!(
+            As2::decode(xt),
+            Ok(meta::client::Packet::AddItem { item_id: 413 })
+        );
+
+        let raw: String = server::Packet(meta::server::Packet::AddItem {
+            item_id: 413,
+            coins: 50,
+        })
+        .into();
+        assert_eq!(raw, "%xt%ai%-1%413%50%");
+    }
+    #[test]
+    fn room_actions() {
+        let xt = XTPacket {
+            handler_id: Some("s".to_owned()),
+            packet_id: "u#sb".to_owned(),
+            internal_id: -1,
+            data: vec!["380".to_owned(), "-4".to_owned()],
+        };
+        assert_eq!(
+            As2::decode(xt),
+            Ok(meta::client::Packet::ThrowSnowball { x: 380, y: -4 })
+        );
+        let xt = XTPacket {
+            handler_id: Some("s".to_owned()),
+            packet_id: "u#ss".to_owned(),
+            internal_id: -1,
+            data: vec!["413".to_owned()],
+        };
+        assert_eq!(
+            As2::decode(xt),
+            Ok(meta::client::Packet::SendSafeMessage { message_id: 413 })
+        );
+
+        let raw: String = server::Packet(meta::server::Packet::SendFrame {
+            player_id: 102,
+            frame: 26,
+        })
+        .into();
+        assert_eq!(raw, "%xt%sf%-1%102%26%");
+    }
+    #[test]
+    fn room_leave() {
+        let raw: String =
+            server::Packet(meta::server::Packet::RemovePlayer { player_id: 102 }).into();
+        assert_eq!(raw, "%xt%rp%-1%102%");
+    }
+    #[test]
+    fn bans() {
+        let raw: String = server::Packet(meta::server::Packet::Banned {
+            hours_left: Some(23),
+        })
+        .into();
+        assert_eq!(raw, "%xt%e%-1%601%23%");
+        let raw: String = server::Packet(meta::server::Packet::Banned {
+            hours_left: Some(0),
+        })
+        .into();
+        assert_eq!(raw, "%xt%e%-1%602%");
+        let raw: String = server::Packet(meta::server::Packet::Banned { hours_left: None }).into();
+        assert_eq!(raw, "%xt%e%-1%603%");
+        let xt = XTPacket {
+            handler_id: Some("s".to_owned()),
+            packet_id: "o#b".to_owned(),
+            internal_id: -1,
+            data: vec!["103".to_owned(), "rude".to_owned(), "0".to_owned()],
+        };
+        assert_eq!(
+            As2::decode(xt),
+            Ok(meta::client::Packet::Ban {
+                player_id: 103,
+                hours: Some(0),
+                message: "rude".to_owned(),
+            })
+        );
+    }
+    #[test]
+    fn buddies() {
+        let raw: String = server::Packet(meta::server::Packet::GetBuddies {
+            buddies: vec![
+                crate::datamodel::Buddy {
+                    id: 103,
+                    nickname: "Basil".to_owned(),
+                    online: true,
+                },
+                crate::datamodel::Buddy {
+                    id: 104,
+                    nickname: "Zed".to_owned(),
+                    online: false,
+                },
+            ],
+        })
+        .into();
+        assert_eq!(raw, "%xt%gb%-1%103|Basil|1%104|Zed|0%");
+        let raw: String =
+            server::Packet(meta::server::Packet::BuddyFound { room_id: Some(100) }).into();
+        assert_eq!(raw, "%xt%bf%-1%100%");
+        let raw: String = server::Packet(meta::server::Packet::BuddyFound { room_id: None }).into();
+        assert_eq!(raw, "%xt%bf%-1%-1%");
+    }
+    #[test]
+    fn mail() {
+        let postcard = crate::datamodel::Postcard {
+            id: 7,
+            sender: Some((102, "Kirill".to_owned())),
+            postcard_type: 1,
+            details: String::new(),
+            sent_at: 1752891915,
+            has_read: false,
+        };
+        let system = crate::datamodel::Postcard {
+            id: 2,
+            sender: None,
+            postcard_type: 112,
+            has_read: true,
+            ..postcard.clone()
+        };
+        let raw: String = server::Packet(meta::server::Packet::GetMail {
+            postcards: vec![postcard.clone(), system],
+        })
+        .into();
+        assert_eq!(
+            raw,
+            "%xt%mg%-1%Kirill|102|1||1752891915|7|0%sys|0|112||1752891915|2|1%"
+        );
+        let raw: String = server::Packet(meta::server::Packet::ReceivePostcard { postcard }).into();
+        assert_eq!(raw, "%xt%mr%-1%Kirill%102%1%%1752891915%7%");
+        let raw: String = server::Packet(meta::server::Packet::PostcardSent {
+            coins: 90,
+            status: meta::server::PostcardStatus::Sent,
+        })
+        .into();
+        assert_eq!(raw, "%xt%ms%-1%90%1%");
+    }
+    #[test]
+    fn ignore_list() {
+        let raw: String = server::Packet(meta::server::Packet::GetIgnoreList {
+            ignored: vec![(103, "Basil".to_owned())],
+        })
+        .into();
+        assert_eq!(raw, "%xt%gn%-1%103|Basil%");
+        let raw: String =
+            server::Packet(meta::server::Packet::GetIgnoreList { ignored: vec![] }).into();
+        assert_eq!(raw, "%xt%gn%-1%%");
+    }
+}
@@ -1 +1,241 @@
-// TODO: impl lol
+use crate::pkt::{
+    meta,
+    xt::{Protocol, XTPacket},
+};
+
+pub struct As3;
+
+impl Protocol for As3 {
+    const NAME: &'static str = "as3";
+
+    fn decode(xt: XTPacket) -> Result<meta::client::Packet, client::PacketError> {
+        client::Packet::try_from(xt).map(|packet| packet.0)
+    }
+
+    fn encode(packet: meta::server::Packet) -> XTPacket {
+        server::Packet(packet).into()
+    }
+}
+
+/* NOTE:
+ * Vanilla clients send the same requests as legacy ones, at least for what we handle.
+ * Add a case here once a request actually diverges, as2 handles the rest.
+ */
+pub mod client {
+    use crate::pkt::{self, xt::as2, xt::XTPacket};
+
+    pub use crate::pkt::xt::as2::client::PacketError;
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Packet(pub pkt::meta::client::Packet);
+
+    impl TryFrom<XTPacket> for Packet {
+        type Error = PacketError;
+        fn try_from(value: XTPacket) -> Result<Self, Self::Error> {
+            let as2::client::Packet(meta) = value.try_into()?;
+            Ok(Packet(meta))
+        }
+    }
+}
+
+pub mod server {
+    use crate::{
+        datamodel::{self, PlayerGist},
+        pkt::{
+            self,
+            xt::{as2, XTPacket, XT_DEFAULT_INT_ID},
+        },
+    };
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Packet(pub pkt::meta::server::Packet);
+
+    impl From<Packet> for String {
+        fn from(val: Packet) -> Self {
+            let xt: XTPacket = val.into();
+            xt.into()
+        }
+    }
+
+    /// as3 flavour of the player string, the trailing states are filled in
+    pub fn gist_string(gist: PlayerGist) -> String {
+        let puffle = match gist.puffle_state.walking {
+            Some(puffle) => format!(
+                "{}|{}|{}|{}",
+                puffle.id, puffle.type_id, puffle.sub_type_id, puffle.hat
+            ),
+            None => "|||".to_owned(),
+        };
+        format!(
+            "{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}",
+            gist.id,
+            gist.nickname,
+            gist.approval as u8,
+            gist.color,
+            gist.head,
+            gist.face,
+            gist.neck,
+            gist.body,
+            gist.hand,
+            gist.feet,
+            gist.flag,
+            gist.photo,
+            gist.x,
+            gist.y,
+            gist.frame,
+            gist.member as u8,
+            gist.membership_days,
+            gist.avatar,
+            gist.penguin_state,
+            gist.party_state,
+            puffle,
+        )
+    }
+
+    // only packets carrying a player string differ from as2
+    impl From<Packet> for XTPacket {
+        fn from(val: Packet) -> Self {
+            match val.0 {
+                pkt::meta::server::Packet::LoadPlayer {
+                    gist,
+                    coins,
+                    safe_chat,
+                    egg_timer_minutes,
+                    penguin_standard_time,
+                    age,
+                    minutes_played,
+                    membership_days_remain,
+                    server_time_offset,
+                    opened_playercard,
+                    map_category,
+                    new_player_status: _,
+                } => XTPacket {
+                    handler_id: None,
+                    packet_id: "lp".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![
+                        gist_string(gist),
+                        coins.to_string(),
+                        (safe_chat as u8).to_string(),
+                        egg_timer_minutes.to_string(),
+                        penguin_standard_time.to_string(),
+                        age.to_string(),
+                        "0".to_owned(),
+                        minutes_played.to_string(),
+                        membership_days_remain.to_string(),
+                        server_time_offset.to_string(),
+                        (opened_playercard as u8).to_string(),
+                        match map_category {
+                            datamodel::MapCategory::Normal => "0".to_owned(),
+                        },
+                        "0".to_owned(),
+                    ],
+                },
+                pkt::meta::server::Packet::JoinRoom { room_id, players } => XTPacket {
+                    handler_id: None,
+                    packet_id: "jr".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: std::iter::once(room_id.to_string())
+                        .chain(players.into_iter().map(gist_string))
+                        .collect(),
+                },
+                pkt::meta::server::Packet::AddedPlayer { player } => XTPacket {
+                    handler_id: None,
+                    packet_id: "ap".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![gist_string(player)],
+                },
+                pkt::meta::server::Packet::GetPlayer { player } => XTPacket {
+                    handler_id: None,
+                    packet_id: "gp".to_owned(),
+                    internal_id: XT_DEFAULT_INT_ID,
+                    data: vec![gist_string(player)],
+                },
+                other => as2::server::Packet(other).into(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        datamodel::{PlayerGist, PlayerPuffleGist, WalkingPuffle},
+        pkt::xt::as2,
+    };
+
+    use super::*;
+
+    fn gist() -> PlayerGist {
+        PlayerGist {
+            id: 102,
+            nickname: "Kirill".to_owned(),
+            approval: false,
+            color: 1,
+            head: 429,
+            face: 0,
+            neck: 0,
+            body: 0,
+            hand: 0,
+            feet: 0,
+            flag: 0,
+            photo: 0,
+            x: 0,
+            y: 0,
+            frame: 1,
+            member: true,
+            membership_days: 9,
+            avatar: 0,
+            penguin_state: "".to_owned(),
+            party_state: "".to_owned(),
+            puffle_state: PlayerPuffleGist::default(),
+        }
+    }
+
+    #[test]
+    fn gist_with_states() {
+        let mut gist = gist();
+        assert_eq!(
+            server::gist_string(gist.clone()),
+            "102|Kirill|0|1|429|0|0|0|0|0|0|0|0|0|1|1|9|0||||||"
+        );
+
+        gist.penguin_state = "1".to_owned();
+        gist.puffle_state.walking = Some(WalkingPuffle {
+            id: 7,
+            type_id: 2,
+            sub_type_id: 0,
+            hat: 0,
+        });
+        assert_eq!(
+            server::gist_string(gist),
+            "102|Kirill|0|1|429|0|0|0|0|0|0|0|0|0|1|1|9|0|1||7|2|0|0"
+        );
+    }
+
+    #[test]
+    fn shares_requests_with_as2() {
+        let xt = XTPacket {
+            handler_id: Some("s".to_owned()),
+            packet_id: "u#sp".to_owned(),
+            internal_id: 1,
+            data: vec!["395".to_owned(), "384".to_owned()],
+        };
+        assert_eq!(
+            As3::decode(xt),
+            Ok(meta::client::Packet::SetPosition { x: 395, y: 384 })
+        );
+    }
+
+    #[test]
+    fn player_strings_differ_from_as2() {
+        let packet = meta::server::Packet::AddedPlayer { player: gist() };
+        let as2: String = as2::server::Packet(packet.clone()).into();
+        let as3: String = server::Packet(packet).into();
+        assert_ne!(as2, as3);
+        assert_eq!(
+            as3,
+            "%xt%ap%-1%102|Kirill|0|1|429|0|0|0|0|0|0|0|0|0|1|1|9|0||||||%"
+        );
+    }
+}
@@ -3,8 +3,18 @@ pub mod as3;
 
 use anyhow::{bail, Error, Result};
 
+use crate::pkt::meta;
+
 const XT_DEFAULT_INT_ID: isize = -1;
 
+/// Flavour of xt a client speaks, legacy (as2) or vanilla (as3)
+pub trait Protocol: Send + Sync + 'static {
+    const NAME: &'static str;
+
+    fn decode(xt: XTPacket) -> Result<meta::client::Packet, as2::client::PacketError>;
+    fn encode(packet: meta::server::Packet) -> XTPacket;
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum XTVariant {
     Server,
@@ -21,7 +31,7 @@ pub struct XTPacket {
     pub(crate) data: Vec<String>,
 }
 
-pub fn deserialize<'raw>(raw: &'raw str, variant: XTVariant) -> Result<XTPacket, Error> {
+pub fn deserialize(raw: &str, variant: XTVariant) -> Result<XTPacket, Error> {
     let raw = match raw {
         raw if !raw.starts_with("%") => bail!("bad leading %"),
         raw if !raw.ends_with("%") => bail!("bad trailing %"),
@@ -36,14 +46,14 @@ pub fn deserialize<'raw>(raw: &'raw str, variant: XTVariant) -> Result<XTPacket,
 
     let handler_id = match variant {
         XTVariant::Client => match iter.next() {
-            Some(hi) if hi.len() > 0 => Some(hi),
+            Some(hi) if !hi.is_empty() => Some(hi),
             _ => bail!("bad extension"),
         },
         XTVariant::Server => None,
     };
 
     let packet_id = match iter.next() {
-        Some(pi) if pi.len() > 0 => pi,
+        Some(pi) if !pi.is_empty() => pi,
         _ => bail!("bad packet id"),
     };
 
@@ -54,7 +64,7 @@ pub fn deserialize<'raw>(raw: &'raw str, variant: XTVariant) -> Result<XTPacket,
 
     let mut data: Vec<String> = Vec::with_capacity(16);
 
-    while let Some(val) = iter.next() {
+    for val in iter {
         data.push(val.to_owned());
     }
 
@@ -84,8 +94,7 @@ pub fn serialize(xt: XTPacket) -> String {
     s.push_str(&xt.internal_id.to_string());
     s.push('%');
 
-    let mut slice_iter = xt.data.iter();
-    while let Some(val) = slice_iter.next() {
+    for val in xt.data.iter() {
         s.push_str(val);
         s.push('%');
     }
@@ -98,9 +107,9 @@ pub fn serialize(xt: XTPacket) -> String {
     s
 }
 
-impl Into<String> for XTPacket {
-    fn into(self) -> String {
-        serialize(self)
+impl From<XTPacket> for String {
+    fn from(val: XTPacket) -> Self {
+        serialize(val)
     }
 }
 
@@ -1,35 +1,56 @@
 pub mod state;
 mod system;
 
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
 
-use tokio::{
-    sync::{broadcast, mpsc},
-    task::AbortHandle,
-};
+use tokio::sync::{broadcast, mpsc};
 
 use crate::{
-    pkt::meta,
+    config::{ClientProtocol, Config, WorldConfig},
+    conn::login_key::KeyStore,
+    datamodel::{self, catalog::Catalog},
+    persistence,
+    pkt::{
+        meta,
+        xt::{as2::As2, as3::As3},
+    },
     server::system::{EventReceiver, EventSender, System},
 };
 use anyhow::{Context, Result};
 
+/* NOTE:
+ * The bus is a broadcast channel, a system falling this far behind loses events.
+ * Room wide packets travel as a single `PacketSentToRoom` to keep bursts small.
+ */
+const BUS_CAPACITY: usize = 1024;
+
+/* NOTE:
+ * Nothing to command yet, a world is run through its systems.
+ * The channel still ties the world's lifetime to its `Handle`.
+ */
 #[derive(Debug, Clone, PartialEq)]
-pub enum ServerCmd {
-    Foo,
-}
+pub enum ServerCmd {}
 
+// packets make up most of the traffic, boxing them buys nothing
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum Event {
     PlayerConnected(meta::PlayerId),
     PlayerDisconnected(meta::PlayerId),
     PacketSent(meta::PlayerId, meta::server::Packet),
+    // one event for a whole room, the socket hands it out to whoever is in there by then
+    PacketSentToRoom(meta::RoomId, state::Audience, meta::server::Packet),
     PacketReceived(meta::PlayerId, meta::client::Packet),
     // TODO: this is a COMMAND not an EVENT
     PlayerTransferRoomRequest(meta::PlayerId, meta::RoomId),
     PlayerJoinedRoom(meta::PlayerId, meta::RoomId),
+    // player is already gone from the room, whether moved on or disconnected
+    PlayerLeftRoom(meta::PlayerId, meta::RoomId),
     Error,
-    DisconnectPlayer,
+    // hang up on the player, whatever it was told last still gets out
+    DisconnectPlayer(meta::PlayerId),
+    // the player may get a postcard for it, online or not, the string ends up in its details
+    Occasion(meta::PlayerId, datamodel::Occasion, String),
     Heartbeat,
 }
 
@@ -37,18 +58,29 @@ pub enum Error {
     PlayerError(meta::PlayerId, anyhow::Error),
 }
 
-pub async fn from_systems(systems: Vec<Box<dyn System>>) -> Result<mpsc::Sender<ServerCmd>> {
+/// What a running world hands back to whoever started it
+pub struct Handle {
+    // dropping it shuts the world down
+    pub cmd_tx: mpsc::Sender<ServerCmd>,
+}
+
+pub async fn from_systems(
+    config: Arc<Config>,
+    catalog: Arc<Catalog>,
+    server_state: state::ServerState,
+    systems: Vec<Box<dyn System>>,
+) -> Result<Handle> {
     /* NOTE:
      * bus_tx is the sole fully owned sender!
      * When dropped all underlying systems are dropped aswell
      */
-    let (bus_tx, _) = broadcast::channel::<Event>(16);
+    let (bus_tx, _) = broadcast::channel::<Event>(BUS_CAPACITY);
     let event_tx = EventSender(bus_tx.downgrade());
 
-    let server_state = state::ServerState::new();
-
     for sys in &systems {
         sys.instantiate(
+            config.clone(),
+            catalog.clone(),
             server_state.clone(),
             event_tx.clone(),
             EventReceiver(bus_tx.subscribe()),
@@ -58,32 +90,63 @@ pub async fn from_systems(systems: Vec<Box<dyn System>>) -> Result<mpsc::Sender<
 
     let (cmd_tx, mut cmd_rx) = mpsc::channel(8);
     tokio::spawn(async move {
-        while let Some(cmd) = cmd_rx.recv().await {
-            match cmd {
-                ServerCmd::Foo => {
-                    log::warn!("bar");
-                }
-            }
+        // only comes back once the handle is dropped
+        if let Some(cmd) = cmd_rx.recv().await {
+            match cmd {}
         }
         drop(bus_tx)
     });
-    Ok(cmd_tx)
+    Ok(Handle { cmd_tx })
 }
 
-pub async fn bind<A>(address: A) -> Result<mpsc::Sender<ServerCmd>>
-where
-    A: ToSocketAddrs,
-{
-    let address = address
-        .to_socket_addrs()?
-        .next()
-        .ok_or_else(|| anyhow::anyhow!("No address found"))?;
+pub async fn bind(
+    world: &WorldConfig,
+    config: Arc<Config>,
+    catalog: Arc<Catalog>,
+    persistence: persistence::Manager,
+    keys: KeyStore,
+) -> Result<Handle> {
+    // whoever was on it before a crash is long gone
+    persistence
+        .clear_presence(world.id)
+        .await
+        .context("failed to clear presence")?;
+    let listeners = world.endpoints();
+    let socket: Box<dyn system::System> = match world.protocol {
+        ClientProtocol::As2 => Box::new(system::socket::Socket::<As2>::new(
+            listeners,
+            persistence.clone(),
+            keys.clone(),
+        )),
+        ClientProtocol::As3 => Box::new(system::socket::Socket::<As3>::new(
+            listeners,
+            persistence.clone(),
+            keys.clone(),
+        )),
+    };
     let systems: Vec<Box<dyn system::System>> = vec![
         Box::new(system::heartbeat::Heartbeat),
-        Box::new(system::socket::as2::Socket { address }),
-        Box::new(system::server::Server),
+        socket,
+        Box::new(system::server::Server {
+            persistence: persistence.clone(),
+            keys,
+            world_id: world.id,
+        }),
+        Box::new(system::chat::Chat {
+            persistence: persistence.clone(),
+        }),
+        Box::new(system::moderation::Moderation {
+            persistence: persistence.clone(),
+        }),
+        Box::new(system::buddy::Buddies {
+            persistence: persistence.clone(),
+        }),
+        Box::new(system::mail::Mail {
+            persistence: persistence.clone(),
+        }),
+        Box::new(system::postman::Postman { persistence }),
     ];
 
-    let tx = from_systems(systems).await?;
-    Ok(tx)
+    let state = state::ServerState::new(&catalog.rooms);
+    from_systems(config, catalog, state, systems).await
 }
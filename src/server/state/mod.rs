@@ -1,56 +1,173 @@
-use std::{
-    collections::HashMap,
-    ops::{Deref, DerefMut},
-    sync::Arc,
-};
+use std::{collections::HashMap, ops::Deref, sync::Arc, time::Instant};
 
 use anyhow::Result;
 use tokio::sync::RwLock;
 
 use crate::{
-    datamodel::{self, RoomId},
+    config::MembershipConfig,
+    datamodel::{
+        self,
+        room::{self, RoomCatalog},
+        ItemId, RoomId,
+    },
+    persistence,
     pkt::meta,
 };
 
+/* NOTE:
+ * Players and rooms reference each other by id only.
+ * `Player::room` and `Room::occupants` must always agree,
+ * so both are only ever changed through the methods of `Server`.
+ */
 #[derive(Debug)]
 pub struct Server {
     penguins: HashMap<meta::PlayerId, Player>,
+    rooms: HashMap<RoomId, Room>,
+}
+
+/// A room as it is right now, broadcasts only go to its occupants
+#[derive(Debug)]
+pub struct Room {
+    // static part from the catalog
+    pub info: room::Room,
+    // room-local state, keyed by occupant
+    spots: HashMap<meta::PlayerId, Spot>,
+}
+
+/// Where an occupant stands and what it is doing, forgotten once it leaves the room
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spot {
+    pub x: isize,
+    pub y: isize,
+    // see `datamodel::action::FRAMES`
+    pub frame: u8,
+}
+
+impl Spot {
+    /// Standing, 0,0 lets the client pick
+    pub fn at(x: isize, y: isize) -> Self {
+        Self { x, y, frame: 1 }
+    }
+}
+
+impl Default for Spot {
+    fn default() -> Self {
+        Self::at(0, 0)
+    }
+}
+
+/// Who in a room gets a packet sent to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Audience {
+    Everyone,
+    // everyone but those ignoring this player
+    HearingFrom(meta::PlayerId),
+}
+
+impl Room {
+    fn new(info: room::Room) -> Self {
+        Self {
+            spots: HashMap::with_capacity(info.capacity),
+            info,
+        }
+    }
+
+    pub fn occupants(&self) -> impl Iterator<Item = meta::PlayerId> + '_ {
+        self.spots.keys().copied()
+    }
+
+    pub fn spot(&self, player_id: meta::PlayerId) -> Option<Spot> {
+        self.spots.get(&player_id).copied()
+    }
+
+    pub fn population(&self) -> usize {
+        self.spots.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.spots.len() >= self.info.capacity
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Player {
     pub id: meta::PlayerId,
-    pub nickname: String,
-    pub room: Option<RoomId>,
-    pub x: isize,
-    pub y: isize,
+    // where it stands is up to the room, see `Spot`
+    room: Option<RoomId>,
+    // chat filter strikes this session
+    pub offenses: usize,
+    // by a moderator, lasts until unmuted or logged off
+    pub muted: bool,
+    // written back to persistence once the player leaves
+    pub penguin: persistence::Penguin,
+    // loaded once on join, kept in sync with persistence from then on
+    pub inventory: Vec<ItemId>,
+    // nicknames by id, whatever these say or do never reaches this player
+    pub ignored: HashMap<meta::PlayerId, String>,
+    pub joined_at: Instant,
+    pub member: bool,
+    pub membership_days: u32,
+}
+
+impl Player {
+    pub fn new(
+        penguin: persistence::Penguin,
+        inventory: Vec<ItemId>,
+        ignored: HashMap<meta::PlayerId, String>,
+        membership: &MembershipConfig,
+    ) -> Self {
+        Self {
+            id: penguin.id,
+            room: None,
+            offenses: 0,
+            muted: false,
+            penguin,
+            inventory,
+            ignored,
+            joined_at: Instant::now(),
+            member: membership.member,
+            membership_days: membership.membership_days,
+        }
+    }
 }
 
-impl Into<datamodel::PlayerGist> for Player {
-    fn into(self) -> datamodel::PlayerGist {
+impl Player {
+    pub fn room(&self) -> Option<RoomId> {
+        self.room
+    }
+
+    pub fn owns(&self, item_id: ItemId) -> bool {
+        self.inventory.contains(&item_id)
+    }
+
+    pub fn ignores(&self, player_id: meta::PlayerId) -> bool {
+        self.ignored.contains_key(&player_id)
+    }
+
+    /// What others get to see of the player, standing at `spot`
+    pub fn gist(&self, spot: Spot) -> datamodel::PlayerGist {
         datamodel::PlayerGist {
             id: self.id,
-            nickname: self.nickname,
+            nickname: self.penguin.nickname.clone(),
             approval: false,
-            color: 1,
-            head: 429,
-            face: 0,
-            neck: 0,
-            body: 0,
-            hand: 0,
-            feet: 0,
-            flag: 0,
-            photo: 0,
-            x: self.x,
-            y: self.y,
-            frame: 1,
-            member: true,
-            membership_days: 9,
+            color: self.penguin.color,
+            head: self.penguin.head,
+            face: self.penguin.face,
+            neck: self.penguin.neck,
+            body: self.penguin.body,
+            hand: self.penguin.hand,
+            feet: self.penguin.feet,
+            flag: self.penguin.flag,
+            photo: self.penguin.photo,
+            x: spot.x,
+            y: spot.y,
+            frame: spot.frame,
+            member: self.member,
+            membership_days: self.membership_days,
             avatar: 0,
-            // TODO: IM
-            // penguin_state: "".to_owned(),
-            // party_state: "".to_owned(),
-            puffle_state: datamodel::PlayerPuffleGist {},
+            penguin_state: "".to_owned(),
+            party_state: "".to_owned(),
+            puffle_state: datamodel::PlayerPuffleGist::default(),
         }
     }
 }
@@ -65,6 +182,18 @@ impl Server {
         Ok(())
     }
 
+    pub fn players(&self) -> impl Iterator<Item = &Player> + '_ {
+        self.penguins.values()
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.penguins.len()
+    }
+
+    pub fn has_player(&self, player_id: meta::PlayerId) -> bool {
+        self.penguins.contains_key(&player_id)
+    }
+
     // TODO: make it a Result<>, if this can be triggered by the player
     pub fn get_player(&self, player_id: meta::PlayerId) -> &Player {
         self.penguins
@@ -72,34 +201,134 @@ impl Server {
             .expect("no such player ... bad state management!")
     }
 
+    /// Case insensitive, as typed into the chat by moderators
+    pub fn find_by_nickname(&self, nickname: &str) -> Option<&Player> {
+        self.penguins
+            .values()
+            .find(|p| p.penguin.nickname.eq_ignore_ascii_case(nickname))
+    }
+
     pub fn get_mut_player(&mut self, player_id: meta::PlayerId) -> &mut Player {
         self.penguins
             .get_mut(&player_id)
             .expect("no such player ... bad state management!")
     }
 
-    pub fn pop_player(&mut self, player_id: meta::PlayerId) -> Result<()> {
+    /// Removes the player from its room too, `Player::room` still tells which one it was
+    pub fn pop_player(&mut self, player_id: meta::PlayerId) -> Result<Player> {
         match self.penguins.remove(&player_id) {
             None => anyhow::bail!("player {} was not in server", player_id),
-            Some(_) => Ok(()),
+            Some(player) => {
+                if let Some(room) = player.room.and_then(|id| self.rooms.get_mut(&id)) {
+                    room.spots.remove(&player_id);
+                }
+                Ok(player)
+            }
         }
     }
 
+    pub fn get_room(&self, room_id: RoomId) -> Option<&Room> {
+        self.rooms.get(&room_id)
+    }
+
+    /// Move a player into a room at `spot`, handing back the room it left
+    pub fn join_room(
+        &mut self,
+        player_id: meta::PlayerId,
+        room_id: RoomId,
+        spot: Spot,
+    ) -> Result<Option<RoomId>, meta::server::Error> {
+        let player = self.get_player(player_id);
+        let room = self
+            .rooms
+            .get(&room_id)
+            .ok_or(meta::server::Error::RoomDoesNotExist)?;
+        if player.room == Some(room_id) {
+            return Err(meta::server::Error::PlayerInRoom);
+        }
+        if room.info.member && !player.member {
+            return Err(meta::server::Error::NotMember);
+        }
+        if room.is_full() {
+            return Err(meta::server::Error::RoomFull);
+        }
+
+        let left = self.leave_room(player_id);
+        self.get_mut_player(player_id).room = Some(room_id);
+        self.rooms
+            .get_mut(&room_id)
+            .expect("room checked above")
+            .spots
+            .insert(player_id, spot);
+        Ok(left)
+    }
+
+    /// Take a player out of its room, if it is in one
+    pub fn leave_room(&mut self, player_id: meta::PlayerId) -> Option<RoomId> {
+        let left = self.get_mut_player(player_id).room.take()?;
+        if let Some(room) = self.rooms.get_mut(&left) {
+            room.spots.remove(&player_id);
+        }
+        Some(left)
+    }
+
+    /// Where the player stands, None outside of rooms
+    pub fn spot_mut(&mut self, player_id: meta::PlayerId) -> Option<&mut Spot> {
+        let room_id = self.penguins.get(&player_id)?.room?;
+        self.rooms.get_mut(&room_id)?.spots.get_mut(&player_id)
+    }
+
+    /// The player as seen by others, right where it stands
+    pub fn gist(&self, player_id: meta::PlayerId) -> datamodel::PlayerGist {
+        let player = self.get_player(player_id);
+        let spot = player
+            .room
+            .and_then(|id| self.rooms.get(&id))
+            .and_then(|room| room.spot(player_id))
+            .unwrap_or_default();
+        player.gist(spot)
+    }
+
     pub fn room_players(&self, room_id: RoomId) -> impl Iterator<Item = &Player> + '_ {
-        self.penguins
-            .iter()
-            .filter(move |(_, p)| p.room == Some(room_id))
-            .map(|(_, p)| p)
+        self.rooms
+            .get(&room_id)
+            .into_iter()
+            .flat_map(|room| room.spots.keys())
+            .map(|id| self.get_player(*id))
+    }
+
+    /// Those in the room a packet for `audience` goes to
+    pub fn audience(
+        &self,
+        room_id: RoomId,
+        audience: Audience,
+    ) -> impl Iterator<Item = meta::PlayerId> + '_ {
+        self.room_players(room_id)
+            .filter(move |p| match audience {
+                Audience::Everyone => true,
+                Audience::HearingFrom(player_id) => !p.ignores(player_id),
+            })
+            .map(|p| p.id)
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct ServerState(Arc<RwLock<Server>>);
 
+impl Default for ServerState {
+    fn default() -> Self {
+        Self::new(&RoomCatalog::default())
+    }
+}
+
 impl ServerState {
-    pub fn new() -> Self {
+    pub fn new(rooms: &RoomCatalog) -> Self {
         let server: Server = Server {
             penguins: HashMap::with_capacity(256),
+            rooms: rooms
+                .iter()
+                .map(|info| (info.id, Room::new(info.clone())))
+                .collect(),
         };
         Self(Arc::new(RwLock::new(server)))
     }
@@ -119,3 +348,139 @@ impl Deref for ServerState {
 // }
 
 // impl
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOMS: &str = r#"{
+        "100": {"room_id": 100, "room_key": "town", "name": "Town", "max_users": 2},
+        "110": {"room_id": 110, "room_key": "coffee", "name": "Coffee Shop", "max_users": 80},
+        "121": {"room_id": 121, "room_key": "lounge", "name": "Lounge", "max_users": 80,
+                "is_member": 1}
+    }"#;
+
+    fn player(id: meta::PlayerId, member: bool) -> Player {
+        let penguin: persistence::Penguin =
+            serde_json::from_str(&format!(r#"{{"id": {id}, "nickname": "p{id}"}}"#)).unwrap();
+        let membership = MembershipConfig {
+            member,
+            ..Default::default()
+        };
+        Player::new(penguin, Vec::new(), HashMap::new(), &membership)
+    }
+
+    fn server() -> Server {
+        let mut server = Server {
+            penguins: HashMap::new(),
+            rooms: HashMap::new(),
+        };
+        for info in RoomCatalog::parse(ROOMS).unwrap().iter() {
+            server.rooms.insert(info.id, Room::new(info.clone()));
+        }
+        for id in [1, 2, 3] {
+            server.push_player(player(id, id != 3)).unwrap();
+        }
+        server
+    }
+
+    fn occupants(server: &Server, room_id: RoomId) -> Vec<meta::PlayerId> {
+        let mut ids: Vec<_> = server.room_players(room_id).map(|p| p.id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn membership_follows_moves() {
+        let mut server = server();
+        assert_eq!(server.join_room(1, 100, Spot::default()), Ok(None));
+        assert_eq!(server.join_room(2, 100, Spot::default()), Ok(None));
+        assert_eq!(occupants(&server, 100), vec![1, 2]);
+
+        assert_eq!(server.join_room(1, 110, Spot::default()), Ok(Some(100)));
+        assert_eq!(occupants(&server, 100), vec![2]);
+        assert_eq!(occupants(&server, 110), vec![1]);
+
+        assert_eq!(server.leave_room(1), Some(110));
+        assert_eq!(server.leave_room(1), None);
+        assert!(occupants(&server, 110).is_empty());
+
+        let popped = server.pop_player(2).unwrap();
+        assert_eq!(popped.room(), Some(100));
+        assert!(occupants(&server, 100).is_empty());
+    }
+
+    #[test]
+    fn join_rules() {
+        let mut server = server();
+        assert_eq!(
+            server.join_room(1, 999, Spot::default()),
+            Err(meta::server::Error::RoomDoesNotExist)
+        );
+        assert_eq!(
+            server.join_room(3, 121, Spot::default()),
+            Err(meta::server::Error::NotMember)
+        );
+
+        server.join_room(1, 100, Spot::default()).unwrap();
+        assert_eq!(
+            server.join_room(1, 100, Spot::default()),
+            Err(meta::server::Error::PlayerInRoom)
+        );
+        server.join_room(2, 100, Spot::default()).unwrap();
+        assert_eq!(
+            server.join_room(3, 100, Spot::default()),
+            Err(meta::server::Error::RoomFull)
+        );
+        // a refused join leaves the player where it was
+        server.join_room(3, 110, Spot::default()).unwrap();
+        assert_eq!(
+            server.join_room(3, 100, Spot::default()),
+            Err(meta::server::Error::RoomFull)
+        );
+        assert_eq!(server.get_player(3).room(), Some(110));
+    }
+
+    #[test]
+    fn ignored_players_go_unheard() {
+        let mut server = server();
+        server.join_room(1, 110, Spot::default()).unwrap();
+        server.join_room(2, 110, Spot::default()).unwrap();
+        server.get_mut_player(2).ignored.insert(1, "p1".to_owned());
+        let audience = |audience| {
+            let mut ids: Vec<_> = server.audience(110, audience).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(audience(Audience::HearingFrom(1)), vec![1]);
+        assert_eq!(audience(Audience::HearingFrom(2)), vec![1, 2]);
+        assert_eq!(audience(Audience::Everyone), vec![1, 2]);
+    }
+
+    #[test]
+    fn spots_stay_in_their_room() {
+        let mut server = server();
+        server.join_room(1, 110, Spot::at(300, 200)).unwrap();
+        server.join_room(2, 110, Spot::default()).unwrap();
+        server.spot_mut(1).unwrap().frame = 26;
+        assert_eq!(
+            server.get_room(110).unwrap().spot(1),
+            Some(Spot {
+                x: 300,
+                y: 200,
+                frame: 26
+            })
+        );
+        assert_eq!(server.gist(1).frame, 26);
+
+        server.join_room(1, 100, Spot::default()).unwrap();
+        assert_eq!(server.get_room(110).unwrap().spot(1), None);
+        assert_eq!(server.gist(1).frame, 1);
+        // whoever left is not told about it anymore
+        assert_eq!(
+            server.audience(110, Audience::Everyone).collect::<Vec<_>>(),
+            vec![2]
+        );
+        assert!(server.spot_mut(3).is_none());
+    }
+}
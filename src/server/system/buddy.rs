@@ -0,0 +1,420 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+use crate::{
+    config::Config,
+    datamodel::{self, catalog::Catalog, PlayerId},
+    persistence,
+    pkt::meta,
+    server::{
+        state,
+        system::{self, EventReceiver, EventSender},
+        Event,
+    },
+};
+
+/// Buddy lists and requests of everyone connected to the world
+pub struct Buddies {
+    pub persistence: persistence::Manager,
+}
+
+/* NOTE:
+ * Kept apart from `state::Player`, the buddy list is this system's own business,
+ * loaded once the world let the penguin in.
+ * Requests are never persisted, they are gone once either side logs off.
+ * All of it is per world: buddies on another world show up as offline,
+ * cannot be asked or found, and are not told when this one comes and goes.
+ * Only the server list looks further, through the persisted presence.
+ */
+struct Online {
+    nickname: String,
+    buddies: HashMap<PlayerId, String>,
+    // who asked this player to be buddies
+    requests: HashSet<PlayerId>,
+}
+
+/// What comes of a player asking or accepting another
+#[derive(Debug, PartialEq)]
+enum Answer {
+    Yes,
+    // one of them has no room for another buddy
+    Full,
+    // nothing to do, or nothing the player may do
+    No,
+}
+
+fn request(
+    online: &HashMap<PlayerId, Online>,
+    player_id: PlayerId,
+    buddy_id: PlayerId,
+    max_buddies: usize,
+) -> Answer {
+    let (Some(player), Some(buddy)) = (online.get(&player_id), online.get(&buddy_id)) else {
+        log::debug!("player {player_id} asked {buddy_id}, who is not here");
+        return Answer::No;
+    };
+    if player_id == buddy_id || player.buddies.contains_key(&buddy_id) {
+        return Answer::No;
+    }
+    if player.buddies.len() >= max_buddies || buddy.buddies.len() >= max_buddies {
+        return Answer::Full;
+    }
+    Answer::Yes
+}
+
+/// Either way the request is used up
+fn accept(
+    online: &mut HashMap<PlayerId, Online>,
+    player_id: PlayerId,
+    buddy_id: PlayerId,
+    max_buddies: usize,
+) -> Answer {
+    let Some(player) = online.get_mut(&player_id) else {
+        return Answer::No;
+    };
+    if !player.requests.remove(&buddy_id) {
+        log::warn!("player {player_id} accepted {buddy_id}, who never asked");
+        return Answer::No;
+    }
+    let player_full = player.buddies.len() >= max_buddies;
+    let Some(buddy) = online.get(&buddy_id) else {
+        return Answer::No;
+    };
+    if player_full || buddy.buddies.len() >= max_buddies {
+        return Answer::Full;
+    }
+    Answer::Yes
+}
+
+async fn load(persistence: &persistence::Manager, player_id: PlayerId) -> Result<Online> {
+    let nickname = persistence
+        .load_penguin(player_id)
+        .await?
+        .with_context(|| format!("penguin {player_id} does not exist"))?
+        .nickname;
+    let buddies = persistence.list_buddies(player_id).await?;
+    let buddies = persistence.nicknames(buddies).await?.into_iter().collect();
+    Ok(Online {
+        nickname,
+        buddies,
+        requests: HashSet::new(),
+    })
+}
+
+#[async_trait]
+impl system::System for Buddies {
+    async fn instantiate(
+        &self,
+        config: Arc<Config>,
+        _catalog: Arc<Catalog>,
+        server: state::ServerState,
+        mut event_tx: EventSender,
+        mut event_rx: EventReceiver,
+    ) -> Result<()> {
+        let persistence = self.persistence.clone();
+        let max_buddies = config.gameplay.max_buddies;
+        tokio::spawn(async move {
+            let mut online: HashMap<PlayerId, Online> = HashMap::new();
+            while let Some(event) = event_rx.poll().await {
+                /* NOTE:
+                 * Connected is not joined, a refused or banned login must never
+                 * show up as online. The first packet after the world let the
+                 * player in does, `b#gb` right after `j#js` with the stock client.
+                 * Packets never trail PlayerDisconnected, unlike server events.
+                 */
+                if let Event::PacketReceived(player_id, _) = event {
+                    if !online.contains_key(&player_id) && server.read().await.has_player(player_id)
+                    {
+                        let player = match load(&persistence, player_id).await {
+                            Ok(player) => player,
+                            Err(e) => {
+                                log::error!("failed to load buddies of {player_id}: {e:#}");
+                                continue;
+                            }
+                        };
+                        for buddy_id in player.buddies.keys() {
+                            if online.contains_key(buddy_id) {
+                                event_tx
+                                    .push(Event::PacketSent(
+                                        *buddy_id,
+                                        meta::server::Packet::BuddyOnline { player_id },
+                                    ))
+                                    .await;
+                            }
+                        }
+                        online.insert(player_id, player);
+                    }
+                }
+                match event {
+                    Event::PlayerDisconnected(player_id) => {
+                        let Some(player) = online.remove(&player_id) else {
+                            continue;
+                        };
+                        for buddy_id in player.buddies.keys() {
+                            if online.contains_key(buddy_id) {
+                                event_tx
+                                    .push(Event::PacketSent(
+                                        *buddy_id,
+                                        meta::server::Packet::BuddyOffline { player_id },
+                                    ))
+                                    .await;
+                            }
+                        }
+                        for other in online.values_mut() {
+                            other.requests.remove(&player_id);
+                        }
+                    }
+                    Event::PacketReceived(player_id, meta::client::Packet::GetBuddies) => {
+                        let Some(player) = online.get(&player_id) else {
+                            continue;
+                        };
+                        let buddies = player
+                            .buddies
+                            .iter()
+                            .map(|(id, nickname)| datamodel::Buddy {
+                                id: *id,
+                                nickname: nickname.clone(),
+                                online: online.contains_key(id),
+                            })
+                            .collect();
+                        event_tx
+                            .push(Event::PacketSent(
+                                player_id,
+                                meta::server::Packet::GetBuddies { buddies },
+                            ))
+                            .await;
+                    }
+                    Event::PacketReceived(
+                        player_id,
+                        meta::client::Packet::BuddyRequest {
+                            player_id: buddy_id,
+                        },
+                    ) => {
+                        match request(&online, player_id, buddy_id, max_buddies) {
+                            Answer::Yes => {}
+                            Answer::Full => {
+                                event_tx
+                                    .push(Event::PacketSent(
+                                        player_id,
+                                        meta::server::Packet::Error(
+                                            meta::server::Error::BuddyLimit,
+                                        ),
+                                    ))
+                                    .await;
+                                continue;
+                            }
+                            Answer::No => continue,
+                        }
+                        let ignored = {
+                            let server = server.read().await;
+                            server.has_player(buddy_id)
+                                && server.get_player(buddy_id).ignores(player_id)
+                        };
+                        if ignored {
+                            log::debug!("player {player_id} asked {buddy_id}, who ignores it");
+                            continue;
+                        }
+                        let nickname = online[&player_id].nickname.clone();
+                        online
+                            .get_mut(&buddy_id)
+                            .expect("checked above")
+                            .requests
+                            .insert(player_id);
+                        event_tx
+                            .push(Event::PacketSent(
+                                buddy_id,
+                                meta::server::Packet::BuddyRequest {
+                                    player_id,
+                                    nickname,
+                                },
+                            ))
+                            .await;
+                    }
+                    Event::PacketReceived(
+                        player_id,
+                        meta::client::Packet::BuddyAccept {
+                            player_id: buddy_id,
+                        },
+                    ) => {
+                        match accept(&mut online, player_id, buddy_id, max_buddies) {
+                            Answer::Yes => {}
+                            Answer::Full => {
+                                event_tx
+                                    .push(Event::PacketSent(
+                                        player_id,
+                                        meta::server::Packet::Error(
+                                            meta::server::Error::BuddyLimit,
+                                        ),
+                                    ))
+                                    .await;
+                                continue;
+                            }
+                            Answer::No => continue,
+                        }
+                        if let Err(e) = persistence.add_buddy(player_id, buddy_id).await {
+                            log::error!("failed to make {player_id} and {buddy_id} buddies: {e:#}");
+                            continue;
+                        }
+                        let buddy_nickname = online[&buddy_id].nickname.clone();
+                        let player = online.get_mut(&player_id).expect("checked above");
+                        player.buddies.insert(buddy_id, buddy_nickname);
+                        let nickname = player.nickname.clone();
+                        online
+                            .get_mut(&buddy_id)
+                            .expect("checked above")
+                            .buddies
+                            .insert(player_id, nickname.clone());
+                        event_tx
+                            .push(Event::PacketSent(
+                                buddy_id,
+                                meta::server::Packet::BuddyAccepted {
+                                    player_id,
+                                    nickname,
+                                },
+                            ))
+                            .await;
+                    }
+                    Event::PacketReceived(
+                        player_id,
+                        meta::client::Packet::BuddyReject {
+                            player_id: buddy_id,
+                        },
+                    ) => {
+                        if let Some(player) = online.get_mut(&player_id) {
+                            player.requests.remove(&buddy_id);
+                        }
+                    }
+                    Event::PacketReceived(
+                        player_id,
+                        meta::client::Packet::BuddyRemove {
+                            player_id: buddy_id,
+                        },
+                    ) => {
+                        let Some(player) = online.get_mut(&player_id) else {
+                            continue;
+                        };
+                        if player.buddies.remove(&buddy_id).is_none() {
+                            continue;
+                        }
+                        let nickname = player.nickname.clone();
+                        if let Err(e) = persistence.remove_buddy(player_id, buddy_id).await {
+                            log::error!("failed to part {player_id} and {buddy_id}: {e:#}");
+                        }
+                        if let Some(buddy) = online.get_mut(&buddy_id) {
+                            buddy.buddies.remove(&player_id);
+                            event_tx
+                                .push(Event::PacketSent(
+                                    buddy_id,
+                                    meta::server::Packet::BuddyRemoved {
+                                        player_id,
+                                        nickname,
+                                    },
+                                ))
+                                .await;
+                        }
+                    }
+                    Event::PacketReceived(
+                        player_id,
+                        meta::client::Packet::FindBuddy {
+                            player_id: buddy_id,
+                        },
+                    ) => {
+                        let Some(player) = online.get(&player_id) else {
+                            continue;
+                        };
+                        if !player.buddies.contains_key(&buddy_id) {
+                            log::warn!("player {player_id} looked for {buddy_id}, not a buddy");
+                            continue;
+                        }
+                        let room_id = match online.get(&buddy_id) {
+                            None => None,
+                            // the other side may have dropped us meanwhile
+                            Some(buddy) if !buddy.buddies.contains_key(&player_id) => {
+                                log::warn!("player {player_id} looked for {buddy_id}, not mutual");
+                                continue;
+                            }
+                            // TODO: igloos, once penguins can be in one
+                            Some(_) => {
+                                let server = server.read().await;
+                                server
+                                    .has_player(buddy_id)
+                                    .then(|| server.get_player(buddy_id).room())
+                                    .flatten()
+                            }
+                        };
+                        event_tx
+                            .push(Event::PacketSent(
+                                player_id,
+                                meta::server::Packet::BuddyFound { room_id },
+                            ))
+                            .await;
+                    }
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn penguin(nickname: &str, buddies: &[PlayerId]) -> Online {
+        Online {
+            nickname: nickname.to_owned(),
+            buddies: buddies.iter().map(|id| (*id, id.to_string())).collect(),
+            requests: HashSet::new(),
+        }
+    }
+
+    #[test]
+    fn accept_needs_a_request() {
+        let mut online =
+            HashMap::from([(102, penguin("Kirill", &[])), (103, penguin("Basil", &[]))]);
+        assert_eq!(accept(&mut online, 103, 102, 100), Answer::No);
+
+        assert_eq!(request(&online, 102, 103, 100), Answer::Yes);
+        online.get_mut(&103).unwrap().requests.insert(102);
+        // only the one asked may accept
+        assert_eq!(accept(&mut online, 102, 103, 100), Answer::No);
+        assert_eq!(accept(&mut online, 103, 102, 100), Answer::Yes);
+        assert_eq!(accept(&mut online, 103, 102, 100), Answer::No);
+
+        assert_eq!(request(&online, 102, 102, 100), Answer::No);
+        assert_eq!(request(&online, 102, 104, 100), Answer::No);
+    }
+
+    #[test]
+    fn buddy_limit() {
+        let mut online = HashMap::from([
+            (102, penguin("Kirill", &[104])),
+            (103, penguin("Basil", &[])),
+        ]);
+        assert_eq!(request(&online, 102, 103, 1), Answer::Full);
+        assert_eq!(request(&online, 103, 102, 1), Answer::Full);
+        assert_eq!(request(&online, 103, 102, 2), Answer::Yes);
+
+        // a full list on either side turns the request down, and uses it up
+        online.get_mut(&102).unwrap().requests.insert(103);
+        assert_eq!(accept(&mut online, 102, 103, 1), Answer::Full);
+        online.get_mut(&103).unwrap().requests.insert(102);
+        assert_eq!(accept(&mut online, 103, 102, 1), Answer::Full);
+        assert_eq!(accept(&mut online, 103, 102, 2), Answer::No);
+    }
+
+    #[test]
+    fn already_buddies() {
+        let online = HashMap::from([
+            (102, penguin("Kirill", &[103])),
+            (103, penguin("Basil", &[102])),
+        ]);
+        assert_eq!(request(&online, 102, 103, 100), Answer::No);
+    }
+}
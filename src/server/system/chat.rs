@@ -0,0 +1,294 @@
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{
+    config::{ChatConfig, Config},
+    datamodel::catalog::Catalog,
+    persistence,
+    pkt::meta,
+    server::{
+        state,
+        system::{self, moderation, EventReceiver, EventSender},
+        Event,
+    },
+};
+
+/// Free text chat, everything else said in a room is pre-built and needs no filtering
+pub struct Chat {
+    // for automatic bans
+    pub persistence: persistence::Manager,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Clean,
+    // tolerated words masked out
+    Masked(String),
+    // not sent, counts as an offense
+    Dropped,
+    // not sent, the player is banned on the spot
+    Banned,
+}
+
+pub struct Filter {
+    tolerate: HashSet<String>,
+    consider: HashSet<String>,
+    ban: HashSet<String>,
+}
+
+impl Filter {
+    pub fn new(config: &ChatConfig) -> Self {
+        let words = |list: &[String]| list.iter().map(|w| w.to_lowercase()).collect();
+        Self {
+            tolerate: words(&config.tolerate),
+            consider: words(&config.consider),
+            ban: words(&config.ban),
+        }
+    }
+
+    pub fn judge(&self, message: &str) -> Verdict {
+        let words: Vec<String> = message
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        if words.iter().any(|w| self.ban.contains(w)) {
+            return Verdict::Banned;
+        }
+        if words.iter().any(|w| self.consider.contains(w)) {
+            return Verdict::Dropped;
+        }
+        if !words.iter().any(|w| self.tolerate.contains(w)) {
+            return Verdict::Clean;
+        }
+
+        let mut masked = String::with_capacity(message.len());
+        let mut word = String::new();
+        let flush = |word: &mut String, masked: &mut String| {
+            if self.tolerate.contains(&word.to_lowercase()) {
+                masked.extend(word.chars().map(|_| '*'));
+            } else {
+                masked.push_str(word);
+            }
+            word.clear();
+        };
+        for c in message.chars() {
+            if c.is_alphanumeric() {
+                word.push(c);
+            } else {
+                flush(&mut word, &mut masked);
+                masked.push(c);
+            }
+        }
+        flush(&mut word, &mut masked);
+        Verdict::Masked(masked)
+    }
+}
+
+/// `!kick <nickname>`, `!mute <nickname>`,
+/// `!ban <nickname> [hours|forever] [reason]` and `!warn <nickname> [reason]`,
+/// `!party <name>` is the odd one out, see `moderator_command`
+fn split_command(message: &str) -> Option<(&str, &str, &str)> {
+    let command = message.strip_prefix('!')?;
+    let (verb, rest) = command.split_once(' ')?;
+    let (nickname, reason) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+    Some((verb, nickname, reason.trim()))
+}
+
+/// Peel the optional `hours` or `forever` off a `!ban` reason,
+/// `None` leaves it to `moderation.ban_hours`
+fn ban_length(reason: &str) -> (Option<u64>, &str) {
+    let (first, rest) = reason.split_once(' ').unwrap_or((reason, ""));
+    if first.eq_ignore_ascii_case("forever") {
+        return (Some(0), rest.trim());
+    }
+    match first.parse() {
+        Ok(hours) => (Some(hours), rest.trim()),
+        Err(_) => (None, reason),
+    }
+}
+
+/// The packet a moderator would have sent instead of typing `message`
+fn moderator_command(server: &state::Server, message: &str) -> Option<meta::client::Packet> {
+    // aimed at nobody in particular, the name may have spaces
+    if let Some(name) = message.strip_prefix("!party ") {
+        return Some(meta::client::Packet::StartParty {
+            name: name.trim().to_owned(),
+        });
+    }
+    let (verb, nickname, reason) = split_command(message)?;
+    let player_id = server.find_by_nickname(nickname)?.id;
+    match verb {
+        "kick" => Some(meta::client::Packet::Kick { player_id }),
+        "mute" => Some(meta::client::Packet::Mute { player_id }),
+        "ban" => {
+            let (hours, reason) = ban_length(reason);
+            Some(meta::client::Packet::Ban {
+                player_id,
+                hours,
+                message: reason.to_owned(),
+            })
+        }
+        "warn" => Some(meta::client::Packet::Warn {
+            player_id,
+            message: reason.to_owned(),
+        }),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl system::System for Chat {
+    async fn instantiate(
+        &self,
+        config: Arc<Config>,
+        _catalog: Arc<Catalog>,
+        server: state::ServerState,
+        mut event_tx: EventSender,
+        mut event_rx: EventReceiver,
+    ) -> Result<()> {
+        let filter = Filter::new(&config.chat);
+        let max_offenses = config.chat.max_offenses;
+        let autoban_hours = config.moderation.autoban_hours;
+        let persistence = self.persistence.clone();
+        tokio::spawn(async move {
+            while let Some(event) = event_rx.poll().await {
+                let Event::PacketReceived(player_id, meta::client::Packet::SendMessage { message }) =
+                    event
+                else {
+                    continue;
+                };
+                let mut server = server.write().await;
+                // connected, but `j#js` not (yet) accepted
+                if !server.has_player(player_id) {
+                    continue;
+                }
+                let player = server.get_mut_player(player_id);
+                let Some(room_id) = player.room() else {
+                    continue;
+                };
+                // the client greys out the chat box, only a modified one gets here
+                if player.penguin.safe_chat {
+                    log::warn!("safe chat player {player_id} sent free text");
+                    continue;
+                }
+                // commands are never said out loud, even unknown ones
+                if player.penguin.moderator.can_moderate() && message.starts_with('!') {
+                    match moderator_command(&server, &message) {
+                        Some(packet) => {
+                            event_tx
+                                .push(Event::PacketReceived(player_id, packet))
+                                .await
+                        }
+                        None => log::info!("moderator {player_id} mistyped {message:?}"),
+                    }
+                    continue;
+                }
+                if player.muted {
+                    log::debug!("muted player {player_id} said {message:?}");
+                    continue;
+                }
+
+                let message = match filter.judge(&message) {
+                    Verdict::Clean => message,
+                    Verdict::Masked(masked) => masked,
+                    verdict => {
+                        player.offenses += 1;
+                        log::info!(
+                            "dropped message of {player_id}, offense {}: {message:?}",
+                            player.offenses
+                        );
+                        let out_of_chances = max_offenses != 0 && player.offenses >= max_offenses;
+                        if verdict != Verdict::Banned && !out_of_chances {
+                            continue;
+                        }
+                        drop(server);
+                        log::warn!("automatically banning {player_id}");
+                        if let Err(e) = moderation::ban(
+                            &persistence,
+                            &mut event_tx,
+                            player_id,
+                            None,
+                            Some(autoban_hours),
+                            format!("chat filter: {message}"),
+                            meta::server::Packet::Error(meta::server::Error::AutoBan),
+                        )
+                        .await
+                        {
+                            log::error!("{e:#}");
+                        }
+                        continue;
+                    }
+                };
+
+                drop(server);
+                event_tx
+                    .push(Event::PacketSentToRoom(
+                        room_id,
+                        state::Audience::HearingFrom(player_id),
+                        meta::server::Packet::SendMessage { player_id, message },
+                    ))
+                    .await;
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter() -> Filter {
+        Filter::new(&ChatConfig {
+            tolerate: vec!["darn".to_owned()],
+            consider: vec!["meanie".to_owned()],
+            ban: vec!["Password".to_owned()],
+            max_offenses: 3,
+        })
+    }
+
+    #[test]
+    fn tiers() {
+        let filter = filter();
+        assert_eq!(filter.judge("hi there"), Verdict::Clean);
+        // whole words only
+        assert_eq!(filter.judge("darned"), Verdict::Clean);
+        assert_eq!(
+            filter.judge("oh DARN, it's cold"),
+            Verdict::Masked("oh ****, it's cold".to_owned())
+        );
+        assert_eq!(filter.judge("you darn meanie"), Verdict::Dropped);
+        assert_eq!(filter.judge("tell me your password"), Verdict::Banned);
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(split_command("!kick Basil"), Some(("kick", "Basil", "")));
+        assert_eq!(
+            split_command("!ban basil  being rude "),
+            Some(("ban", "basil", "being rude"))
+        );
+        assert_eq!(
+            split_command("!warn Basil stop that"),
+            Some(("warn", "Basil", "stop that"))
+        );
+        assert_eq!(split_command("!kick"), None);
+        assert_eq!(ban_length("being rude"), (None, "being rude"));
+        assert_eq!(ban_length("72 being rude"), (Some(72), "being rude"));
+        assert_eq!(ban_length("Forever"), (Some(0), ""));
+        assert_eq!(ban_length(""), (None, ""));
+        assert_eq!(
+            moderator_command(
+                &state::ServerState::default().blocking_read(),
+                "!party  Summer Luau "
+            ),
+            Some(meta::client::Packet::StartParty {
+                name: "Summer Luau".to_owned()
+            })
+        );
+        assert_eq!(split_command("kick basil"), None);
+    }
+}
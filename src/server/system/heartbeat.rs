@@ -1,7 +1,13 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
-use crate::server::{
-    self, state, system::{EventReceiver, EventSender}, Event
+use crate::{
+    config::Config,
+    datamodel::catalog::Catalog,
+    server::{
+        self, state,
+        system::{EventReceiver, EventSender},
+        Event,
+    },
 };
 use anyhow::Result;
 use async_trait::async_trait;
@@ -12,6 +18,8 @@ pub struct Heartbeat;
 impl server::system::System for Heartbeat {
     async fn instantiate(
         &self,
+        _config: Arc<Config>,
+        _catalog: Arc<Catalog>,
         _server: state::ServerState,
         mut event_tx: EventSender,
         mut event_rx: EventReceiver,
@@ -24,12 +32,8 @@ impl server::system::System for Heartbeat {
         });
 
         tokio::spawn(async move {
-            while let Some(event) = event_rx.poll().await {
-                match event {
-                    // Event::Heartbeat => log::debug!("heartbeat received"),
-                    _ => {}
-                }
-            }
+            // Event::Heartbeat => log::debug!("heartbeat received"),
+            while event_rx.poll().await.is_some() {}
         });
 
         Ok(())
@@ -0,0 +1,228 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+use crate::{
+    config::Config,
+    datamodel::{catalog::Catalog, PlayerId, Postcard},
+    persistence,
+    pkt::meta::{self, server::PostcardStatus},
+    server::{
+        state,
+        system::{self, EventReceiver, EventSender},
+        Event,
+    },
+};
+
+/// Postcards, sent by penguins for coins or by the server for free
+pub struct Mail {
+    pub persistence: persistence::Manager,
+}
+
+/// What came of `deliver`
+#[derive(Debug, PartialEq)]
+pub enum Delivery {
+    Delivered,
+    MailboxFull,
+    NoSuchPenguin,
+}
+
+/// Drop `postcard` into the mailbox of `recipient_id`, telling it right away if online
+pub async fn deliver(
+    persistence: &persistence::Manager,
+    server: &state::ServerState,
+    event_tx: &mut EventSender,
+    mailbox_size: usize,
+    recipient_id: PlayerId,
+    postcard: Postcard,
+) -> Result<Delivery> {
+    if persistence.load_penguin(recipient_id).await?.is_none() {
+        return Ok(Delivery::NoSuchPenguin);
+    }
+    let Some(id) = persistence
+        .send_postcard(recipient_id, &postcard, mailbox_size)
+        .await
+        .with_context(|| format!("failed to deliver a postcard to {recipient_id}"))?
+    else {
+        return Ok(Delivery::MailboxFull);
+    };
+    if server.read().await.has_player(recipient_id) {
+        event_tx
+            .push(Event::PacketSent(
+                recipient_id,
+                meta::server::Packet::ReceivePostcard {
+                    postcard: Postcard { id, ..postcard },
+                },
+            ))
+            .await;
+    }
+    Ok(Delivery::Delivered)
+}
+
+#[async_trait]
+impl system::System for Mail {
+    async fn instantiate(
+        &self,
+        config: Arc<Config>,
+        _catalog: Arc<Catalog>,
+        server: state::ServerState,
+        mut event_tx: EventSender,
+        mut event_rx: EventReceiver,
+    ) -> Result<()> {
+        let persistence = self.persistence.clone();
+        let cost = config.mail.postcard_cost;
+        let mailbox_size = config.mail.mailbox_size;
+        tokio::spawn(async move {
+            while let Some(event) = event_rx.poll().await {
+                let Event::PacketReceived(player_id, packet) = event else {
+                    continue;
+                };
+                match packet {
+                    meta::client::Packet::StartMailEngine => {
+                        let postcards = match persistence.list_postcards(player_id).await {
+                            Ok(postcards) => postcards,
+                            Err(e) => {
+                                log::error!("failed to open the mailbox of {player_id}: {e:#}");
+                                continue;
+                            }
+                        };
+                        event_tx
+                            .push(Event::PacketSent(
+                                player_id,
+                                meta::server::Packet::StartMailEngine {
+                                    unread_mail_count: postcards
+                                        .iter()
+                                        .filter(|p| !p.has_read)
+                                        .count(),
+                                    mail_count: postcards.len(),
+                                },
+                            ))
+                            .await;
+                    }
+                    meta::client::Packet::GetMail => {
+                        let postcards = match persistence.list_postcards(player_id).await {
+                            Ok(postcards) => postcards,
+                            Err(e) => {
+                                log::error!("failed to open the mailbox of {player_id}: {e:#}");
+                                continue;
+                            }
+                        };
+                        event_tx
+                            .push(Event::PacketSent(
+                                player_id,
+                                meta::server::Packet::GetMail { postcards },
+                            ))
+                            .await;
+                    }
+                    meta::client::Packet::SendPostcard {
+                        recipient_id,
+                        postcard_type,
+                    } => {
+                        // TODO: check postcard_type, once there are postcard crumbs
+                        /* NOTE:
+                         * paid up front, under the same lock as the check,
+                         * and handed back should the postcard not go out
+                         */
+                        let nickname = {
+                            let mut server = server.write().await;
+                            if !server.has_player(player_id) {
+                                continue;
+                            }
+                            let player = server.get_mut_player(player_id);
+                            if player.penguin.coins < cost {
+                                let coins = player.penguin.coins;
+                                drop(server);
+                                event_tx
+                                    .push(Event::PacketSent(
+                                        player_id,
+                                        meta::server::Packet::PostcardSent {
+                                            coins,
+                                            status: PostcardStatus::NotEnoughCoins,
+                                        },
+                                    ))
+                                    .await;
+                                continue;
+                            }
+                            player.penguin.coins -= cost;
+                            player.penguin.nickname.clone()
+                        };
+                        let postcard = Postcard {
+                            id: 0,
+                            sender: Some((player_id, nickname)),
+                            postcard_type,
+                            details: String::new(),
+                            sent_at: persistence::now(),
+                            has_read: false,
+                        };
+                        let status = match deliver(
+                            &persistence,
+                            &server,
+                            &mut event_tx,
+                            mailbox_size,
+                            recipient_id,
+                            postcard,
+                        )
+                        .await
+                        {
+                            Ok(Delivery::Delivered) => PostcardStatus::Sent,
+                            Ok(Delivery::MailboxFull) => PostcardStatus::MailboxFull,
+                            Ok(Delivery::NoSuchPenguin) => {
+                                log::warn!("player {player_id} wrote to {recipient_id}, nobody");
+                                PostcardStatus::NoSuchPenguin
+                            }
+                            // the client has nothing better to say
+                            Err(e) => {
+                                log::error!("{e:#}");
+                                PostcardStatus::MailboxFull
+                            }
+                        };
+
+                        let mut server = server.write().await;
+                        if !server.has_player(player_id) {
+                            continue;
+                        }
+                        let player = server.get_mut_player(player_id);
+                        if status != PostcardStatus::Sent {
+                            player.penguin.coins += cost;
+                        }
+                        let coins = player.penguin.coins;
+                        let penguin = player.penguin.clone();
+                        drop(server);
+                        if status == PostcardStatus::Sent {
+                            if let Err(e) = persistence.save_penguin(&penguin).await {
+                                log::error!("failed to save coins of {player_id}: {e:#}");
+                            }
+                        }
+                        event_tx
+                            .push(Event::PacketSent(
+                                player_id,
+                                meta::server::Packet::PostcardSent { coins, status },
+                            ))
+                            .await;
+                    }
+                    meta::client::Packet::ReadPostcards => {
+                        if let Err(e) = persistence.read_postcards(player_id).await {
+                            log::error!("failed to read the mailbox of {player_id}: {e:#}");
+                        }
+                    }
+                    meta::client::Packet::DeletePostcard { postcard_id } => {
+                        if let Err(e) = persistence.delete_postcard(player_id, postcard_id).await {
+                            log::error!("failed to delete postcard {postcard_id}: {e:#}");
+                        }
+                    }
+                    meta::client::Packet::DeletePostcardsFrom { sender_id } => {
+                        if let Err(e) = persistence
+                            .delete_postcards_from(player_id, sender_id)
+                            .await
+                        {
+                            log::error!("failed to delete postcards from {sender_id}: {e:#}");
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+}
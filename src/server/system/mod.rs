@@ -1,17 +1,31 @@
+pub mod buddy;
+pub mod chat;
 pub mod heartbeat;
+pub mod mail;
+pub mod moderation;
+pub mod postman;
 pub mod server;
 pub mod socket;
 
+use std::sync::Arc;
+
 use anyhow::Result;
 use async_trait::async_trait;
 use tokio::sync::broadcast::{self, error::RecvError};
 
-use crate::server::{state, Event};
+use crate::{
+    config::Config,
+    datamodel::catalog::Catalog,
+    server::{state, Event},
+};
 
 #[async_trait]
 pub trait System {
     async fn instantiate(
         &self,
+        config: Arc<Config>,
+        // static game data, the same for every world
+        catalog: Arc<Catalog>,
         server: state::ServerState,
         tx: EventSender,
         rx: EventReceiver,
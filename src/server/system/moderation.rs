@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+use crate::{
+    config::Config,
+    datamodel::{catalog::Catalog, Occasion, PlayerId},
+    persistence,
+    pkt::meta,
+    server::{
+        state,
+        system::{self, EventReceiver, EventSender},
+        Event,
+    },
+};
+
+/// Kick, mute, ban and warn, also announcing parties, for moderators only
+pub struct Moderation {
+    pub persistence: persistence::Manager,
+}
+
+/// Tell the player why, then hang up on it
+pub async fn kick(event_tx: &mut EventSender, player_id: PlayerId, notice: meta::server::Packet) {
+    event_tx.push(Event::PacketSent(player_id, notice)).await;
+    event_tx.push(Event::DisconnectPlayer(player_id)).await;
+}
+
+/// Persist a ban of `hours`, `None` for good, and kick the player,
+/// `notice` is what it gets told
+pub async fn ban(
+    persistence: &persistence::Manager,
+    event_tx: &mut EventSender,
+    player_id: PlayerId,
+    moderator_id: Option<PlayerId>,
+    hours: Option<u64>,
+    reason: String,
+    notice: meta::server::Packet,
+) -> Result<()> {
+    let now = persistence::now();
+    persistence
+        .add_ban(&persistence::Ban {
+            penguin_id: player_id,
+            issued_at: now,
+            expires_at: hours.map(|hours| now + hours * 60 * 60),
+            moderator_id,
+            reason,
+        })
+        .await
+        .with_context(|| format!("failed to ban {player_id}"))?;
+    kick(event_tx, player_id, notice).await;
+    Ok(())
+}
+
+fn is_moderator(server: &state::Server, player_id: PlayerId) -> bool {
+    let is_moderator = server.has_player(player_id)
+        && server
+            .get_player(player_id)
+            .penguin
+            .moderator
+            .can_moderate();
+    if !is_moderator {
+        log::warn!("player {player_id} tried to moderate without being a moderator");
+    }
+    is_moderator
+}
+
+/* NOTE:
+ * Moderators act on penguins online in the same world only,
+ * and never on each other.
+ */
+fn may_moderate(server: &state::Server, moderator_id: PlayerId, player_id: PlayerId) -> bool {
+    if !is_moderator(server, moderator_id) {
+        return false;
+    }
+    if !server.has_player(player_id) {
+        log::info!("moderator {moderator_id} went after {player_id}, who is not here");
+        return false;
+    }
+    if server
+        .get_player(player_id)
+        .penguin
+        .moderator
+        .can_moderate()
+    {
+        log::warn!("moderator {moderator_id} went after fellow moderator {player_id}");
+        return false;
+    }
+    true
+}
+
+#[async_trait]
+impl system::System for Moderation {
+    async fn instantiate(
+        &self,
+        config: Arc<Config>,
+        _catalog: Arc<Catalog>,
+        server: state::ServerState,
+        mut event_tx: EventSender,
+        mut event_rx: EventReceiver,
+    ) -> Result<()> {
+        let persistence = self.persistence.clone();
+        tokio::spawn(async move {
+            while let Some(event) = event_rx.poll().await {
+                let Event::PacketReceived(moderator_id, packet) = event else {
+                    continue;
+                };
+                match packet {
+                    meta::client::Packet::Kick { player_id } => {
+                        if !may_moderate(&*server.read().await, moderator_id, player_id) {
+                            continue;
+                        }
+                        log::info!("moderator {moderator_id} kicks {player_id}");
+                        kick(
+                            &mut event_tx,
+                            player_id,
+                            meta::server::Packet::Error(meta::server::Error::Kick),
+                        )
+                        .await;
+                    }
+                    meta::client::Packet::Mute { player_id } => {
+                        let mut server = server.write().await;
+                        if !may_moderate(&server, moderator_id, player_id) {
+                            continue;
+                        }
+                        let player = server.get_mut_player(player_id);
+                        player.muted = !player.muted;
+                        log::info!(
+                            "moderator {moderator_id} {} {player_id}",
+                            if player.muted { "mutes" } else { "unmutes" }
+                        );
+                    }
+                    meta::client::Packet::Ban {
+                        player_id,
+                        hours,
+                        message,
+                    } => {
+                        if !may_moderate(&*server.read().await, moderator_id, player_id) {
+                            continue;
+                        }
+                        let hours = match hours.unwrap_or(config.moderation.ban_hours) {
+                            0 => None,
+                            hours => Some(hours),
+                        };
+                        log::info!(
+                            "moderator {moderator_id} bans {player_id} for {}: {message}",
+                            hours.map_or("good".to_owned(), |hours| format!("{hours}h"))
+                        );
+                        if let Err(e) = ban(
+                            &persistence,
+                            &mut event_tx,
+                            player_id,
+                            Some(moderator_id),
+                            hours,
+                            message,
+                            meta::server::Packet::Banned { hours_left: hours },
+                        )
+                        .await
+                        {
+                            log::error!("{e:#}");
+                        }
+                    }
+                    // what the player is told is up to the `mail.system` rules
+                    meta::client::Packet::Warn { player_id, message } => {
+                        if !may_moderate(&*server.read().await, moderator_id, player_id) {
+                            continue;
+                        }
+                        log::info!("moderator {moderator_id} warns {player_id}: {message}");
+                        event_tx
+                            .push(Event::Occasion(player_id, Occasion::Warned, message))
+                            .await;
+                    }
+                    // the whole world is told, what by is up to the `mail.system` rules
+                    meta::client::Packet::StartParty { name } => {
+                        let online: Vec<_> = {
+                            let server = server.read().await;
+                            if !is_moderator(&server, moderator_id) || name.is_empty() {
+                                continue;
+                            }
+                            server.players().map(|p| p.id).collect()
+                        };
+                        log::info!("moderator {moderator_id} starts the {name} party");
+                        for player_id in online {
+                            event_tx
+                                .push(Event::Occasion(
+                                    player_id,
+                                    Occasion::PartyStarted,
+                                    name.clone(),
+                                ))
+                                .await;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{
+    config::Config,
+    datamodel::{catalog::Catalog, Postcard},
+    persistence,
+    server::{
+        state,
+        system::{self, mail, EventReceiver, EventSender},
+        Event,
+    },
+};
+
+/// Postcards from the system, sent on occasions as the `mail.system` rules say
+pub struct Postman {
+    pub persistence: persistence::Manager,
+}
+
+/// Fill in the `details` template of a `config::SystemPostcard`
+fn render(template: &str, nickname: &str, details: &str) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("{nickname}") {
+            rendered.push_str(nickname);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{details}") {
+            rendered.push_str(details);
+            rest = after;
+        } else {
+            rendered.push('{');
+            rest = &rest[1..];
+        }
+    }
+    rendered.push_str(rest);
+    // the details travel in a field of `mg`, separators would tear it apart
+    rendered.replace(['|', '%'], "")
+}
+
+#[async_trait]
+impl system::System for Postman {
+    async fn instantiate(
+        &self,
+        config: Arc<Config>,
+        _catalog: Arc<Catalog>,
+        server: state::ServerState,
+        mut event_tx: EventSender,
+        mut event_rx: EventReceiver,
+    ) -> Result<()> {
+        let persistence = self.persistence.clone();
+        tokio::spawn(async move {
+            let rules = &config.mail.system;
+            while let Some(event) = event_rx.poll().await {
+                let Event::Occasion(player_id, occasion, details) = event else {
+                    continue;
+                };
+                let matching: Vec<_> = rules.iter().filter(|rule| rule.on == occasion).collect();
+                if matching.is_empty() {
+                    continue;
+                }
+                // offline penguins get their postcards too
+                let nickname = match persistence.load_penguin(player_id).await {
+                    Ok(Some(penguin)) => penguin.nickname,
+                    Ok(None) => {
+                        log::warn!("no postcard for {occasion:?}, penguin {player_id} is gone");
+                        continue;
+                    }
+                    Err(e) => {
+                        log::error!("failed to load penguin {player_id}: {e:#}");
+                        continue;
+                    }
+                };
+                for rule in matching {
+                    let postcard = Postcard {
+                        id: 0,
+                        sender: None,
+                        postcard_type: rule.postcard_type,
+                        details: render(&rule.details, &nickname, &details),
+                        sent_at: persistence::now(),
+                        has_read: false,
+                    };
+                    match mail::deliver(
+                        &persistence,
+                        &server,
+                        &mut event_tx,
+                        config.mail.mailbox_size,
+                        player_id,
+                        postcard,
+                    )
+                    .await
+                    {
+                        Ok(mail::Delivery::Delivered) => {
+                            log::info!("sent {player_id} a postcard for {occasion:?}")
+                        }
+                        Ok(mail::Delivery::MailboxFull) => log::info!(
+                            "no postcard for {occasion:?}, {player_id}'s mailbox is full"
+                        ),
+                        Ok(mail::Delivery::NoSuchPenguin) => {
+                            log::warn!("no postcard for {occasion:?}, penguin {player_id} is gone")
+                        }
+                        Err(e) => log::error!("{e:#}"),
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates() {
+        assert_eq!(render("", "Basil", "x"), "");
+        assert_eq!(
+            render("Hi {nickname}, {details}!", "Basil", "be nice"),
+            "Hi Basil, be nice!"
+        );
+        // filled in once, whatever the details say
+        assert_eq!(render("{details}", "Basil", "{nickname}"), "{nickname}");
+        assert_eq!(render("{party} {", "Basil", ""), "{party} {");
+        assert_eq!(render("{details}", "Basil", "a|b%c"), "abc");
+    }
+}
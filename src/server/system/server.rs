@@ -1,34 +1,53 @@
 use std::{
-    fs::read,
+    sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::Result;
 use async_trait::async_trait;
+use rand::seq::IndexedRandom;
 
 use crate::{
-    datamodel::{self},
+    config::Config,
+    conn::login_key::KeyStore,
+    datamodel::{self, action, catalog::Catalog},
+    persistence,
     pkt::meta,
     server::{
         state,
-        system::{self, EventReceiver, EventSender},
+        system::{self, moderation, EventReceiver, EventSender},
         Event,
     },
 };
 
-pub struct Server;
+pub struct Server {
+    pub persistence: persistence::Manager,
+    pub keys: KeyStore,
+    pub world_id: datamodel::WorldId,
+}
 
 #[async_trait]
 impl system::System for Server {
     async fn instantiate(
         &self,
+        config: Arc<Config>,
+        catalog: Arc<Catalog>,
         server: state::ServerState,
         mut event_tx: EventSender,
         mut event_rx: EventReceiver,
     ) -> Result<()> {
+        let persistence = self.persistence.clone();
+        let keys = self.keys.clone();
+        let world_id = self.world_id;
         tokio::spawn(async move {
             loop {
                 while let Some(event) = event_rx.poll().await {
+                    if let Some(player_id) = needs_player(&event) {
+                        if !server.read().await.has_player(player_id) {
+                            log::debug!("player {player_id} has not joined, dropping {event:?}");
+                            continue;
+                        }
+                    }
                     match event {
                         Event::PlayerConnected(player_id) => {
                             log::info!("player {player_id} connected!");
@@ -40,73 +59,341 @@ impl system::System for Server {
                         }
                         Event::PlayerDisconnected(player_id) => {
                             log::info!("player {player_id} disconnected");
-                            server.write().await.pop_player(player_id).unwrap();
-                            // TODO: UPDATE CONNECTED PEOPLE
+                            // never joined, or already gone
+                            let Ok(player) = server.write().await.pop_player(player_id) else {
+                                continue;
+                            };
+                            if let Some(room_id) = player.room() {
+                                event_tx
+                                    .push(Event::PlayerLeftRoom(player_id, room_id))
+                                    .await;
+                            }
+                            let state::Player {
+                                mut penguin,
+                                joined_at,
+                                ..
+                            } = player;
+                            penguin.minutes_played += (joined_at.elapsed().as_secs() / 60) as usize;
+                            if let Err(e) = persistence.save_penguin(&penguin).await {
+                                log::error!("failed to save penguin {player_id}: {e:#}");
+                            }
+                            if let Err(e) = persistence.set_presence(player_id, None).await {
+                                log::error!("failed to clear presence of {player_id}: {e:#}");
+                            }
                         }
                         Event::PacketReceived(player_id, meta::client::Packet::GetIgnoreList) => {
+                            let ignored = server
+                                .read()
+                                .await
+                                .get_player(player_id)
+                                .ignored
+                                .iter()
+                                .map(|(id, nickname)| (*id, nickname.clone()))
+                                .collect();
                             event_tx
                                 .push(Event::PacketSent(
                                     player_id,
-                                    meta::server::Packet::GetIgnoreList {},
+                                    meta::server::Packet::GetIgnoreList { ignored },
                                 ))
                                 .await;
                         }
+                        Event::PacketReceived(
+                            player_id,
+                            meta::client::Packet::AddIgnore {
+                                player_id: ignore_id,
+                            },
+                        ) => {
+                            if ignore_id == player_id
+                                || server.read().await.get_player(player_id).ignores(ignore_id)
+                            {
+                                continue;
+                            }
+                            let nickname = match persistence.nicknames(vec![ignore_id]).await {
+                                Ok(nicknames) => match nicknames.into_iter().next() {
+                                    Some((_, nickname)) => nickname,
+                                    None => {
+                                        log::warn!(
+                                            "player {player_id} ignored unknown {ignore_id}"
+                                        );
+                                        continue;
+                                    }
+                                },
+                                Err(e) => {
+                                    log::error!("failed to look up {ignore_id}: {e:#}");
+                                    continue;
+                                }
+                            };
+                            if let Err(e) = persistence.add_ignore(player_id, ignore_id).await {
+                                log::error!("failed to let {player_id} ignore {ignore_id}: {e:#}");
+                                continue;
+                            }
+                            server
+                                .write()
+                                .await
+                                .get_mut_player(player_id)
+                                .ignored
+                                .insert(ignore_id, nickname);
+                        }
+                        Event::PacketReceived(
+                            player_id,
+                            meta::client::Packet::RemoveIgnore {
+                                player_id: ignore_id,
+                            },
+                        ) => {
+                            if let Err(e) = persistence.remove_ignore(player_id, ignore_id).await {
+                                log::error!(
+                                    "failed to let {player_id} unignore {ignore_id}: {e:#}"
+                                );
+                                continue;
+                            }
+                            server
+                                .write()
+                                .await
+                                .get_mut_player(player_id)
+                                .ignored
+                                .remove(&ignore_id);
+                        }
 
                         Event::PacketReceived(
                             player_id,
                             meta::client::Packet::SetPosition { x, y },
                         ) => {
                             let mut server = server.write().await;
-                            let player = server.get_mut_player(player_id);
-                            let room_id = player.room.unwrap();
-                            player.x = x;
-                            player.y = y;
-
-                            for e in
-                                server
-                                    .room_players(room_id)
-                                    .map(|state::Player { id, .. }| {
-                                        Event::PacketSent(
-                                            *id,
-                                            meta::server::Packet::SetPosition {
-                                                player_id: *id,
-                                                x,
-                                                y,
-                                            },
-                                        )
-                                    })
-                            {
-                                event_tx.push(e).await;
+                            let Some(room_id) = server.get_player(player_id).room() else {
+                                continue;
+                            };
+                            if !action::on_stage(x, y) {
+                                log::warn!("player {player_id} tried to walk off stage to {x},{y}");
+                                continue;
                             }
+                            // walking off stands the penguin up again
+                            *server.spot_mut(player_id).expect("in a room") = state::Spot::at(x, y);
 
-                            // TODO: update frame and toy!!
+                            event_tx
+                                .push(to_room(
+                                    room_id,
+                                    meta::server::Packet::SetPosition { player_id, x, y },
+                                ))
+                                .await;
+                            // TODO: toys
                         }
-                        Event::PacketReceived(player_id, meta::client::Packet::GetInventory) => {
+                        Event::PacketReceived(
+                            player_id,
+                            meta::client::Packet::SendFrame { frame },
+                        ) => {
+                            let mut server = server.write().await;
+                            let Some(room_id) = server.get_player(player_id).room() else {
+                                continue;
+                            };
+                            if !action::FRAMES.contains(&frame) {
+                                log::warn!("player {player_id} sent unknown frame {frame}");
+                                continue;
+                            }
+                            // kept so players joining later see it sitting, dancing, ...
+                            server.spot_mut(player_id).expect("in a room").frame = frame;
+                            event_tx
+                                .push(to_room(
+                                    room_id,
+                                    meta::server::Packet::SendFrame { player_id, frame },
+                                ))
+                                .await;
+                        }
+                        Event::PacketReceived(
+                            player_id,
+                            meta::client::Packet::SendEmote { emote_id },
+                        ) => {
                             let server = server.read().await;
-                            let _player = server.get_player(player_id);
-                            let items = vec![1, 429, 9057, 339, 609, 8009];
+                            let Some(room_id) = server.get_player(player_id).room() else {
+                                continue;
+                            };
+                            if !action::EMOTES.contains(&emote_id) {
+                                log::warn!("player {player_id} sent unknown emote {emote_id}");
+                                continue;
+                            }
                             event_tx
-                                .push(Event::PacketSent(
+                                .push(to_audience(
+                                    room_id,
                                     player_id,
-                                    meta::server::Packet::GetInventory { items },
+                                    meta::server::Packet::SendEmote {
+                                        player_id,
+                                        emote_id,
+                                    },
                                 ))
                                 .await;
                         }
-                        Event::PacketReceived(player_id, meta::client::Packet::GetBuddies) => {
+                        Event::PacketReceived(
+                            player_id,
+                            meta::client::Packet::SendAction { action_id },
+                        ) => {
+                            let server = server.read().await;
+                            let Some(room_id) = server.get_player(player_id).room() else {
+                                continue;
+                            };
+                            if !action::ACTIONS.contains(&action_id) {
+                                log::warn!("player {player_id} sent unknown action {action_id}");
+                                continue;
+                            }
+                            event_tx
+                                .push(to_room(
+                                    room_id,
+                                    meta::server::Packet::SendAction {
+                                        player_id,
+                                        action_id,
+                                    },
+                                ))
+                                .await;
+                        }
+                        Event::PacketReceived(
+                            player_id,
+                            meta::client::Packet::ThrowSnowball { x, y },
+                        ) => {
+                            let server = server.read().await;
+                            let Some(room_id) = server.get_player(player_id).room() else {
+                                continue;
+                            };
+                            if !action::on_stage(x, y) {
+                                log::warn!(
+                                    "player {player_id} threw a snowball off stage to {x},{y}"
+                                );
+                                continue;
+                            }
+                            event_tx
+                                .push(to_room(
+                                    room_id,
+                                    meta::server::Packet::ThrowSnowball { player_id, x, y },
+                                ))
+                                .await;
+                        }
+                        Event::PacketReceived(
+                            player_id,
+                            meta::client::Packet::SendJoke { joke_id },
+                        ) => {
+                            let server = server.read().await;
+                            let Some(room_id) = server.get_player(player_id).room() else {
+                                continue;
+                            };
+                            if !action::JOKES.contains(&joke_id) {
+                                log::warn!("player {player_id} sent unknown joke {joke_id}");
+                                continue;
+                            }
+                            event_tx
+                                .push(to_audience(
+                                    room_id,
+                                    player_id,
+                                    meta::server::Packet::SendJoke { player_id, joke_id },
+                                ))
+                                .await;
+                        }
+                        Event::PacketReceived(player_id, meta::client::Packet::GetInventory) => {
+                            let items = server.read().await.get_player(player_id).inventory.clone();
                             event_tx
                                 .push(Event::PacketSent(
                                     player_id,
-                                    meta::server::Packet::GetBuddies {},
+                                    meta::server::Packet::GetInventory { items },
                                 ))
                                 .await;
                         }
-                        Event::PacketReceived(player_id, meta::client::Packet::StartMailEngine) => {
+                        Event::PacketReceived(
+                            player_id,
+                            meta::client::Packet::UpdateOutfit { slot, item_id },
+                        ) => {
+                            let mut server = server.write().await;
+                            let player = server.get_mut_player(player_id);
+                            let allowed = if item_id == 0 {
+                                slot.may_be_empty()
+                            } else {
+                                player.owns(item_id)
+                                    && catalog
+                                        .items
+                                        .get(item_id)
+                                        .is_some_and(|item| item.kind.slot() == Some(slot))
+                            };
+                            if !allowed {
+                                log::warn!(
+                                    "player {player_id} tried to wear {item_id} as {slot:?}, which it does not own or fit"
+                                );
+                                continue;
+                            }
+                            *player.penguin.slot_mut(slot) = item_id;
+                            let penguin = player.penguin.clone();
+                            let room_id = player.room();
+                            drop(server);
+
+                            if let Some(room_id) = room_id {
+                                event_tx
+                                    .push(to_room(
+                                        room_id,
+                                        meta::server::Packet::UpdatedOutfit {
+                                            player_id,
+                                            slot,
+                                            item_id,
+                                        },
+                                    ))
+                                    .await;
+                            }
+                            if let Err(e) = persistence.save_penguin(&penguin).await {
+                                log::error!("failed to save outfit of {player_id}: {e:#}");
+                            }
+                        }
+                        Event::PacketReceived(
+                            player_id,
+                            meta::client::Packet::AddItem { item_id },
+                        ) => {
+                            let mut server = server.write().await;
+                            let player = server.get_mut_player(player_id);
+                            let bought = match catalog.items.get(item_id) {
+                                None => Err(meta::server::Error::ItemNotExist),
+                                Some(item) if !item.available => {
+                                    Err(meta::server::Error::ItemNotAvailable)
+                                }
+                                Some(item) if item.member && !player.member => {
+                                    Err(meta::server::Error::NotMember)
+                                }
+                                Some(_) if player.owns(item_id) => {
+                                    Err(meta::server::Error::AlreadyOwnInventoryItem)
+                                }
+                                Some(item) if item.cost > player.penguin.coins => {
+                                    Err(meta::server::Error::NotEnoughCoins)
+                                }
+                                Some(item) => Ok(item.cost),
+                            };
+                            let cost = match bought {
+                                Ok(cost) => cost,
+                                Err(error) => {
+                                    log::debug!(
+                                        "player {player_id} may not buy {item_id}: {error:?}"
+                                    );
+                                    event_tx
+                                        .push(Event::PacketSent(
+                                            player_id,
+                                            meta::server::Packet::Error(error),
+                                        ))
+                                        .await;
+                                    continue;
+                                }
+                            };
+                            player.penguin.coins -= cost;
+                            player.inventory.push(item_id);
+                            let penguin = player.penguin.clone();
+                            drop(server);
+
+                            /* NOTE:
+                             * the item is recorded before the coins are taken,
+                             * should the second write fail the player got it for free
+                             * rather than paid for nothing
+                             */
+                            if let Err(e) = persistence.add_inventory_item(player_id, item_id).await
+                            {
+                                log::error!("failed to store item {item_id} of {player_id}: {e:#}");
+                            } else if let Err(e) = persistence.save_penguin(&penguin).await {
+                                log::error!("failed to save coins of {player_id}: {e:#}");
+                            }
                             event_tx
                                 .push(Event::PacketSent(
                                     player_id,
-                                    meta::server::Packet::StartMailEngine {
-                                        unread_mail_count: 0,
-                                        mail_count: 2,
+                                    meta::server::Packet::AddItem {
+                                        item_id,
+                                        coins: penguin.coins,
                                     },
                                 ))
                                 .await;
@@ -149,15 +436,9 @@ impl system::System for Server {
                             event_tx
                                 .push(Event::PacketSent(
                                     player_id,
-                                    meta::server::Packet::GetLastRevision("houdini".to_owned()),
-                                ))
-                                .await;
-                        }
-                        Event::PacketReceived(player_id, meta::client::Packet::GetMail) => {
-                            event_tx
-                                .push(Event::PacketSent(
-                                    player_id,
-                                    meta::server::Packet::GetMail {},
+                                    meta::server::Packet::GetLastRevision(
+                                        config.gameplay.revision.clone(),
+                                    ),
                                 ))
                                 .await;
                         }
@@ -176,10 +457,77 @@ impl system::System for Server {
                                 ))
                                 .await;
                         }
+                        Event::PacketReceived(
+                            player_id,
+                            meta::client::Packet::JoinRoom { room_id, x, y },
+                        ) => {
+                            let mut server = server.write().await;
+                            // games are not walked into, whatever the client says
+                            if server.get_room(room_id).is_some_and(|room| room.info.game) {
+                                log::warn!("player {player_id} tried to walk into game {room_id}");
+                                event_tx
+                                    .push(Event::PacketSent(
+                                        player_id,
+                                        meta::server::Packet::Error(
+                                            meta::server::Error::InvalidRoomIdSpecifiedInJJr,
+                                        ),
+                                    ))
+                                    .await;
+                                continue;
+                            }
+                            // standing wherever it walked in, as long as that is on stage
+                            if !action::on_stage(x, y) {
+                                log::warn!(
+                                    "player {player_id} tried to walk in off stage at {x},{y}"
+                                );
+                            }
+                            let spot = state::Spot::at(
+                                x.clamp(0, action::STAGE_WIDTH),
+                                y.clamp(0, action::STAGE_HEIGHT),
+                            );
+                            let left = match server.join_room(player_id, room_id, spot) {
+                                Ok(left) => left,
+                                Err(error) => {
+                                    log::debug!(
+                                        "player {player_id} may not join {room_id}: {error:?}"
+                                    );
+                                    event_tx
+                                        .push(Event::PacketSent(
+                                            player_id,
+                                            meta::server::Packet::Error(error),
+                                        ))
+                                        .await;
+                                    continue;
+                                }
+                            };
+                            if let Some(left) = left {
+                                event_tx.push(Event::PlayerLeftRoom(player_id, left)).await;
+                            }
+                            event_tx
+                                .push(Event::PlayerJoinedRoom(player_id, room_id))
+                                .await;
+                        }
                         Event::PlayerTransferRoomRequest(player_id, room_id) => {
                             let mut server = server.write().await;
-                            let player = server.get_mut_player(player_id);
-                            player.room = Some(room_id);
+                            let spot = state::Spot::default();
+                            let left = match server.join_room(player_id, room_id, spot) {
+                                Ok(left) => left,
+                                Err(error) => {
+                                    log::warn!(
+                                        "player {player_id} could not be moved to {room_id}: {error:?}"
+                                    );
+                                    event_tx
+                                        .push(Event::PacketSent(
+                                            player_id,
+                                            meta::server::Packet::Error(error),
+                                        ))
+                                        .await;
+                                    continue;
+                                }
+                            };
+                            if let Some(left) = left {
+                                event_tx.push(Event::PlayerLeftRoom(player_id, left)).await;
+                            }
                             event_tx
                                 .push(Event::PlayerJoinedRoom(player_id, room_id))
                                 .await;
@@ -189,8 +537,13 @@ impl system::System for Server {
                             meta::client::Packet::GetPlayer { player },
                         ) => {
                             let server = server.read().await;
-                            // TODO: player can crash server!
-                            let player = server.get_player(player).clone().into();
+                            if !server.has_player(player) {
+                                log::debug!(
+                                    "player {player_id} asked for {player}, who is not here"
+                                );
+                                continue;
+                            }
+                            let player = server.gist(player);
                             event_tx
                                 .push(Event::PacketSent(
                                     player_id,
@@ -212,12 +565,11 @@ impl system::System for Server {
                         Event::PlayerJoinedRoom(player_id, room_id) => {
                             let server = server.read().await;
 
-                            let joiner_gist: datamodel::PlayerGist =
-                                server.get_player(player_id).clone().into();
+                            let joiner_gist = server.gist(player_id);
 
                             let gists: Vec<datamodel::PlayerGist> = server
                                 .room_players(room_id)
-                                .map(|p| p.clone().into())
+                                .map(|p| server.gist(p.id))
                                 .collect();
                             event_tx
                                 .push(Event::PacketSent(
@@ -229,59 +581,234 @@ impl system::System for Server {
                                 ))
                                 .await;
 
-                            for event in
-                                server
-                                    .room_players(room_id)
-                                    .map(|state::Player { id, .. }| {
-                                        Event::PacketSent(
-                                            *id,
-                                            meta::server::Packet::AddedPlayer {
-                                                player: joiner_gist.clone(),
-                                            },
-                                        )
-                                    })
-                            {
-                                event_tx.push(event).await;
+                            event_tx
+                                .push(to_room(
+                                    room_id,
+                                    meta::server::Packet::AddedPlayer {
+                                        player: joiner_gist,
+                                    },
+                                ))
+                                .await;
+                        }
+                        Event::PlayerLeftRoom(player_id, room_id) => {
+                            // it is out of the room already, no need to skip it
+                            event_tx
+                                .push(to_room(
+                                    room_id,
+                                    meta::server::Packet::RemovePlayer { player_id },
+                                ))
+                                .await;
+                        }
+                        Event::PacketReceived(
+                            player_id,
+                            meta::client::Packet::SendSafeMessage { message_id },
+                        ) => {
+                            let server = server.read().await;
+                            let Some(room_id) = server.get_player(player_id).room() else {
+                                continue;
+                            };
+                            if !action::SAFE_MESSAGES.contains(&message_id) {
+                                log::warn!(
+                                    "player {player_id} sent unknown safe message {message_id}"
+                                );
+                                continue;
                             }
+                            event_tx
+                                .push(to_audience(
+                                    room_id,
+                                    player_id,
+                                    meta::server::Packet::SendSafeMessage {
+                                        player_id,
+                                        message_id,
+                                    },
+                                ))
+                                .await;
                         }
                         Event::PacketReceived(
                             player_id,
-                            meta::client::Packet::SendMessage { message },
+                            meta::client::Packet::SendLineMessage { line_id },
                         ) => {
                             let server = server.read().await;
-                            let room = server.get_player(player_id).room.unwrap();
-                            for e in server.room_players(room).map(|p| {
-                                Event::PacketSent(
-                                    p.id,
-                                    meta::server::Packet::SendMessage {
+                            let Some(room_id) = server.get_player(player_id).room() else {
+                                continue;
+                            };
+                            if !action::LINE_MESSAGES.contains(&line_id) {
+                                log::warn!("player {player_id} sent unknown line {line_id}");
+                                continue;
+                            }
+                            event_tx
+                                .push(to_audience(
+                                    room_id,
+                                    player_id,
+                                    meta::server::Packet::SendLineMessage { player_id, line_id },
+                                ))
+                                .await;
+                        }
+                        Event::PacketReceived(
+                            player_id,
+                            meta::client::Packet::SendTourMessage { message_id },
+                        ) => {
+                            let server = server.read().await;
+                            let Some(room_id) = server.get_player(player_id).room() else {
+                                continue;
+                            };
+                            if !action::TOUR_MESSAGES.contains(&message_id) {
+                                log::warn!(
+                                    "player {player_id} sent unknown tour message {message_id}"
+                                );
+                                continue;
+                            }
+                            event_tx
+                                .push(to_audience(
+                                    room_id,
+                                    player_id,
+                                    meta::server::Packet::SendTourMessage {
                                         player_id,
-                                        message: message.clone(),
+                                        message_id,
                                     },
-                                )
-                            }) {
-                                event_tx.push(e).await;
+                                ))
+                                .await;
+                        }
+                        Event::PacketReceived(
+                            player_id,
+                            meta::client::Packet::SendGuideMessage { message_id },
+                        ) => {
+                            let server = server.read().await;
+                            let player = server.get_player(player_id);
+                            let Some(room_id) = player.room() else {
+                                continue;
+                            };
+                            if !action::GUIDE_MESSAGES.contains(&message_id) {
+                                log::warn!(
+                                    "player {player_id} sent unknown guide message {message_id}"
+                                );
+                                continue;
                             }
+                            if !player.owns(action::TOUR_GUIDE_HAT)
+                                && !player.penguin.moderator.can_moderate()
+                            {
+                                log::warn!("player {player_id} sent a guide message, not a guide");
+                                continue;
+                            }
+                            event_tx
+                                .push(to_audience(
+                                    room_id,
+                                    player_id,
+                                    meta::server::Packet::SendGuideMessage {
+                                        player_id,
+                                        message_id,
+                                    },
+                                ))
+                                .await;
                         }
 
                         Event::PacketReceived(
                             player_id,
                             meta::client::Packet::JoinServer {
                                 penguin_id,
-                                login_key: _,
+                                login_key,
                                 language: _,
                             },
                         ) => {
-                            // TODO: load from DB
-                            let player = state::Player {
-                                id: player_id,
-                                room: None,
-                                nickname: "kirill".to_owned(),
-                                x: 0,
-                                y: 0,
+                            if server.read().await.has_player(player_id) {
+                                log::warn!("player {player_id} tried to join twice");
+                                continue;
+                            }
+                            if penguin_id != player_id {
+                                log::warn!("player {player_id} tried to join as {penguin_id}");
+                                moderation::kick(
+                                    &mut event_tx,
+                                    player_id,
+                                    meta::server::Packet::Error(meta::server::Error::PasswordWrong),
+                                )
+                                .await;
+                                continue;
+                            }
+                            if let Err(e) = keys.confirm(player_id, &login_key).await {
+                                log::warn!("player {player_id} failed to join: {e}");
+                                moderation::kick(
+                                    &mut event_tx,
+                                    player_id,
+                                    meta::server::Packet::Error(e.into()),
+                                )
+                                .await;
+                                continue;
+                            }
+                            let mut penguin = match persistence.load_penguin(player_id).await {
+                                Ok(Some(penguin)) => penguin,
+                                Ok(None) => {
+                                    log::warn!("authenticated player {player_id} has no penguin");
+                                    moderation::kick(
+                                        &mut event_tx,
+                                        player_id,
+                                        meta::server::Packet::Error(
+                                            meta::server::Error::NameNotFound,
+                                        ),
+                                    )
+                                    .await;
+                                    continue;
+                                }
+                                Err(e) => {
+                                    log::error!("failed to load penguin {player_id}: {e:#}");
+                                    moderation::kick(
+                                        &mut event_tx,
+                                        player_id,
+                                        meta::server::Packet::Error(
+                                            meta::server::Error::NoDbConnection,
+                                        ),
+                                    )
+                                    .await;
+                                    continue;
+                                }
                             };
+                            let inventory = match persistence.list_inventory(player_id).await {
+                                Ok(inventory) => inventory,
+                                Err(e) => {
+                                    log::error!("failed to load inventory of {player_id}: {e:#}");
+                                    moderation::kick(
+                                        &mut event_tx,
+                                        player_id,
+                                        meta::server::Packet::Error(
+                                            meta::server::Error::NoDbConnection,
+                                        ),
+                                    )
+                                    .await;
+                                    continue;
+                                }
+                            };
+                            let ignored = match persistence.list_ignored(player_id).await {
+                                Ok(ignored) => persistence.nicknames(ignored).await,
+                                Err(e) => Err(e),
+                            };
+                            let ignored = match ignored {
+                                Ok(ignored) => ignored.into_iter().collect(),
+                                Err(e) => {
+                                    log::error!("failed to load ignore list of {player_id}: {e:#}");
+                                    moderation::kick(
+                                        &mut event_tx,
+                                        player_id,
+                                        meta::server::Packet::Error(
+                                            meta::server::Error::NoDbConnection,
+                                        ),
+                                    )
+                                    .await;
+                                    continue;
+                                }
+                            };
+                            // saved right away, a world going down saves nobody
+                            let first_login = penguin.last_login_at.is_none();
+                            penguin.last_login_at = Some(persistence::now());
+                            if let Err(e) = persistence.save_penguin(&penguin).await {
+                                log::error!("failed to save login of {player_id}: {e:#}");
+                            }
+                            let player = state::Player::new(
+                                penguin,
+                                inventory,
+                                ignored,
+                                &config.gameplay.membership,
+                            );
 
-                            // TODO: what if player is already connected
-                            // TODO: handle login ket
+                            // a second connection never gets this far, see `socket::dist`
                             event_tx
                                 .push(Event::PacketSent(
                                     player_id,
@@ -293,20 +820,23 @@ impl system::System for Server {
                                 .push(Event::PacketSent(
                                     player_id,
                                     meta::server::Packet::LoadPlayer {
-                                        gist: player.clone().into(),
-                                        coins: 100,
-                                        safe_chat: false,
-                                        egg_timer_minutes: 100,
+                                        gist: player.gist(state::Spot::default()),
+                                        coins: player.penguin.coins,
+                                        safe_chat: player.penguin.safe_chat,
+                                        egg_timer_minutes: config.gameplay.egg_timer_minutes,
                                         penguin_standard_time: (SystemTime::now()
                                             .duration_since(UNIX_EPOCH)
                                             .expect("time not available?")
                                             .as_secs()
                                             * 1000)
                                             as usize,
-                                        age: 0,
-                                        minutes_played: 10,
-                                        membership_days_remain: 1000,
-                                        server_time_offset: 7,
+                                        age: penguin_age_days(&player.penguin),
+                                        minutes_played: player.penguin.minutes_played,
+                                        membership_days_remain: config
+                                            .gameplay
+                                            .membership
+                                            .membership_days_remain,
+                                        server_time_offset: config.gameplay.server_time_offset,
                                         opened_playercard: true,
                                         map_category: datamodel::MapCategory::Normal,
                                         new_player_status: datamodel::NewPlayerStatus {},
@@ -314,13 +844,19 @@ impl system::System for Server {
                                 ))
                                 .await;
 
+                            let moderator_status = player.penguin.moderator;
                             server.write().await.push_player(player).unwrap();
+                            if let Err(e) =
+                                persistence.set_presence(penguin_id, Some(world_id)).await
+                            {
+                                log::error!("failed to set presence of {penguin_id}: {e:#}");
+                            }
                             event_tx
                                 .push(Event::PacketSent(
                                     penguin_id,
                                     meta::server::Packet::JoinedServer {
                                         agent_status: false,
-                                        moderator_status: meta::ModeratorStatus::None,
+                                        moderator_status,
                                         book_modified: false,
                                     },
                                 ))
@@ -332,8 +868,23 @@ impl system::System for Server {
                                 ))
                                 .await;
 
+                            if first_login {
+                                event_tx
+                                    .push(Event::Occasion(
+                                        player_id,
+                                        datamodel::Occasion::FirstLogin,
+                                        String::new(),
+                                    ))
+                                    .await;
+                            }
+
+                            let spawn = *config
+                                .gameplay
+                                .spawn_rooms
+                                .choose(&mut rand::rng())
+                                .expect("config guarantees spawn rooms");
                             event_tx
-                                .push(Event::PlayerTransferRoomRequest(player_id, 230))
+                                .push(Event::PlayerTransferRoomRequest(player_id, spawn))
                                 .await;
                         }
 
@@ -347,6 +898,43 @@ impl system::System for Server {
     }
 }
 
+fn penguin_age_days(penguin: &persistence::Penguin) -> usize {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time not available?")
+        .as_secs();
+    (now.saturating_sub(penguin.registered_at) / (60 * 60 * 24)) as usize
+}
+
+/* NOTE:
+ * Connected is not joined, a client may send anything before `j#js`
+ * (or after a refused one), and room events may still be on the bus
+ * once the player is gone. These need a `state::Player`.
+ */
+fn needs_player(event: &Event) -> Option<meta::PlayerId> {
+    match event {
+        Event::PacketReceived(_, meta::client::Packet::JoinServer { .. }) => None,
+        Event::PacketReceived(player_id, _)
+        | Event::PlayerTransferRoomRequest(player_id, _)
+        | Event::PlayerJoinedRoom(player_id, _) => Some(*player_id),
+        _ => None,
+    }
+}
+
+/// One copy of `packet` for everyone in `room_id`, whoever caused it included
+fn to_room(room_id: datamodel::RoomId, packet: meta::server::Packet) -> Event {
+    Event::PacketSentToRoom(room_id, state::Audience::Everyone, packet)
+}
+
+/// Like `to_room`, but skips whoever ignores `player_id`
+fn to_audience(
+    room_id: datamodel::RoomId,
+    player_id: meta::PlayerId,
+    packet: meta::server::Packet,
+) -> Event {
+    Event::PacketSentToRoom(room_id, state::Audience::HearingFrom(player_id), packet)
+}
+
 // current_time = int(time.time())
 // penguin_standard_time = current_time * 1000
 //
@@ -1,8 +1,14 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{Context, Result};
 
 use crate::{
-    conn::line::{self, LineConnReader, LineConnWriter},
-    pkt::{self, meta},
+    config::PolicyConfig,
+    conn::{
+        line::{self, LineConnReader, LineConnWriter},
+        login::{LoginHandler, LoginResp, Verification},
+        login_key::KeyStore,
+    },
+    persistence,
+    pkt::{meta, xt::Protocol},
 };
 
 pub enum AuthResult {
@@ -10,11 +16,14 @@ pub enum AuthResult {
     Authenticated(meta::PlayerId),
 }
 
-pub async fn gate(
+pub async fn gate<P: Protocol>(
     writer: LineConnWriter,
     reader: LineConnReader,
+    persistence: &persistence::Manager,
+    keys: &KeyStore,
+    policy: &PolicyConfig,
 ) -> Result<(AuthResult, LineConnWriter, LineConnReader)> {
-    match login_loop(writer, reader)
+    match login_loop::<P>(writer, reader, persistence, keys, policy)
         .await
         // TODO: log connection?
         .context("failure in login loop")?
@@ -26,52 +35,50 @@ pub async fn gate(
     }
 }
 
-async fn login_loop(
+async fn login_loop<P: Protocol>(
     writer: LineConnWriter,
     reader: LineConnReader,
+    persistence: &persistence::Manager,
+    keys: &KeyStore,
+    policy: &PolicyConfig,
 ) -> Result<(Option<meta::PlayerId>, LineConnWriter, LineConnReader)> {
     let mut writer = writer;
     let mut reader = reader;
+    let mut handler = LoginHandler::new(
+        persistence.clone(),
+        Verification::LoginKey(keys.clone()),
+        policy.clone(),
+    )
+    .await?;
 
-    let (username, _password) = loop {
-        log::info!("waiting for input????");
-        let line = reader.read().await;
-        match line {
+    loop {
+        let packet = match reader.read().await {
             // TODO: BAD: user error and server error are not differentiated
             Err(line::ReadError::EnvError(e)) => return Err(e),
             Err(line::ReadError::ParseError(e)) => {
                 log::warn!("line is not parseable xml: {}", e);
                 continue;
             }
-            Ok(None) => {
-                return Err(anyhow!("login loop was quit early!"));
-            }
-            Ok(Some(pkt::xml::client::Packet::VersionCheck { expected })) => {
-                log::info!("client expects version {expected}");
-                writer.write(pkt::xml::server::Packet::ApiOK).await.unwrap();
-            }
+            // flash hangs up right after fetching the policy file
+            Ok(None) => return Ok((None, writer, reader)),
+            Ok(Some(packet)) => packet,
+        };
 
-            Ok(Some(pkt::xml::client::Packet::RandomKey)) => {
+        match handler.handle(&packet).await? {
+            LoginResp::Packet(packet) => writer.write(packet).await?,
+            LoginResp::HandShook(account) => return Ok((Some(account.id), writer, reader)),
+            LoginResp::Rejected(error) => {
                 writer
-                    .write(pkt::xml::server::Packet::RandomKey("houdini".to_owned()))
-                    .await
-                    .unwrap();
+                    .write(P::encode(meta::server::Packet::Error(error)))
+                    .await?;
+                return Ok((None, writer, reader));
             }
-
-            Ok(Some(pkt::xml::client::Packet::Login { username, password })) => {
-                break (username, password);
+            LoginResp::Banned { hours_left } => {
+                writer
+                    .write(P::encode(meta::server::Packet::Banned { hours_left }))
+                    .await?;
+                return Ok((None, writer, reader));
             }
         }
-    };
-
-    if &username != "kirill" {
-        writer
-            .write(pkt::xt::as2::server::Packet(meta::server::Packet::Error(
-                meta::server::Error::NameNotFound,
-            )))
-            .await
-            .unwrap();
-        return Ok((None, writer, reader));
     }
-    Ok((Some(102), writer, reader))
 }
@@ -1,17 +1,23 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 
 use anyhow::{Context, Result};
 use tokio::{
-    net::{TcpListener, TcpStream},
+    net::TcpStream,
     sync::{mpsc, RwLock},
 };
 use tokio_util::sync::CancellationToken;
 
 use crate::{
-    conn::line,
+    config::PolicyConfig,
+    conn::{
+        line,
+        listener::{self, Listener, Transport},
+        login_key::KeyStore,
+    },
+    persistence,
     pkt::{
-        meta::{self, PlayerId},
-        xt::XTPacket,
+        meta,
+        xt::{Protocol, XTPacket},
     },
     server::system::socket::authgate::{self, AuthResult},
 };
@@ -22,14 +28,21 @@ pub enum Event {
     Disconnected,
 }
 
+struct Connection {
+    writer: line::LineConnWriter,
+    // stops the reader, and with it the connection
+    cancel: CancellationToken,
+}
+
 /* Unless we have a good reason to change,
  * we only store connections that are authenticated!
- * TODO: We likely should also implement some timeout / protection system
+ * Each newcomer logs in on its own task, within `LOGIN_TIMEOUT`.
+ * TODO: We likely should also implement some rate limiting
  * otherwise a malicious actor could overload the scheduler!
  * ... and we would have no idea who it was!!
  */
 pub struct Distributed {
-    connections: Arc<RwLock<HashMap<meta::PlayerId, line::LineConnWriter>>>,
+    connections: Arc<RwLock<HashMap<meta::PlayerId, Connection>>>,
     rx: mpsc::Receiver<(meta::PlayerId, Event)>,
 
     // notify actual sockets to close their connection
@@ -38,8 +51,13 @@ pub struct Distributed {
 
 impl Distributed {
     // todo: split into sub functions
-    pub async fn new(socket: TcpListener) -> Self {
-        let connections: Arc<RwLock<HashMap<meta::PlayerId, line::LineConnWriter>>> =
+    pub async fn new<P: Protocol>(
+        listeners: Vec<Listener>,
+        persistence: persistence::Manager,
+        keys: KeyStore,
+        policy: PolicyConfig,
+    ) -> Self {
+        let connections: Arc<RwLock<HashMap<meta::PlayerId, Connection>>> =
             Arc::new(RwLock::new(HashMap::with_capacity(64)));
 
         let (tx, rx) = mpsc::channel::<(meta::PlayerId, Event)>(32);
@@ -48,47 +66,69 @@ impl Distributed {
             let connections = connections.clone();
             let cancel = cancel.clone();
             async move {
+                let (admitted_tx, mut admitted_rx) = mpsc::channel(32);
                 loop {
-                    let (addr, (writer, reader)) = tokio::select! {
+                    let (addr, player_id, writer, mut reader) = tokio::select! {
                         _ = cancel.cancelled() => break,
-                        conn_res =  socket.accept() => match conn_res{
+                        Some(admitted) = admitted_rx.recv() => admitted,
+                        conn_res = listener::accept_any(&listeners) => match conn_res{
                             Err(e) => todo!("handle failure to accept tcp connections {e}"),
-                            Ok((stream, addr)) => (addr, line::line_con(stream).await),
-                        }
-                    };
-                    log::debug!("accepted connection from {addr}");
-
-                    let (player_id, writer, mut reader) = match authgate::gate(writer, reader).await
-                    {
-                        Ok((AuthResult::Unauthenticated, _, _)) => {
-                            log::warn!("Bad auth result for {addr}, discarding");
-                            continue;
-                        }
-                        Ok((authgate::AuthResult::Authenticated(player_id), writer, reader)) => {
-                            (player_id, writer, reader)
+                            Ok((stream, addr, transport)) => {
+                                log::debug!("accepted connection from {addr}");
+                                // one slow client must not hold up everyone else
+                                tokio::spawn(admit::<P>(
+                                    stream,
+                                    addr,
+                                    transport,
+                                    persistence.clone(),
+                                    keys.clone(),
+                                    policy.clone(),
+                                    admitted_tx.clone(),
+                                ));
+                                continue;
+                            }
                         }
-                        Err(e) => {
-                            log::error!("FUCK: {:#}", e);
-                            todo!("")
-                        } //todo!("handle auth failure: {e}"),
                     };
                     let mut conn_map = connections.write().await;
-                    if let Some(_) = conn_map.insert(player_id, writer) {
-                        todo!("player already connected to server! HANDLE!");
+                    /* NOTE:
+                     * The one already here stays, kicking it instead would have
+                     * its goodbye race the newcomer's hello, both go by player id.
+                     */
+                    if conn_map.contains_key(&player_id) {
+                        drop(conn_map);
+                        log::warn!("player {player_id} is connected already, refusing {addr}");
+                        let mut writer = writer;
+                        let refusal = P::encode(meta::server::Packet::Error(
+                            meta::server::Error::MultiConnections,
+                        ));
+                        if let Err(e) = writer.write(refusal).await {
+                            log::debug!("failed to refuse {addr}: {e:#}");
+                        }
+                        continue;
                     }
+                    let conn_cancel = cancel.child_token();
+                    conn_map.insert(
+                        player_id,
+                        Connection {
+                            writer,
+                            cancel: conn_cancel.clone(),
+                        },
+                    );
                     log::info!("player {player_id} connected with address {addr}");
 
                     tokio::spawn({
                         let connections = connections.clone();
                         let tx = tx.clone();
-                        let cancel = cancel.clone();
+                        let cancel = conn_cancel;
                         async move {
-                            if let Err(_) = tx.send((player_id, Event::Connected)).await {
+                            if tx.send((player_id, Event::Connected)).await.is_err() {
                                 return;
                             };
                             loop {
                                 let xt_res = tokio::select! {
                                     _ = cancel.cancelled() => {
+                                        // kicked, the world still has to let go of the player
+                                        let _ = tx.send((player_id, Event::Disconnected)).await;
                                         break;
                                     }
                                     res = reader.read::<XTPacket>() => res
@@ -100,18 +140,20 @@ impl Distributed {
                                         break;
                                     }
                                     Ok(Some(xt)) => {
-                                        if let Err(_) =
-                                            tx.send((player_id, Event::Packet(xt))).await
-                                        {
+                                        if tx.send((player_id, Event::Packet(xt))).await.is_err() {
                                             break;
                                         }
                                     }
-                                    Err(_) => todo!("failure in read xt"),
+                                    // an entry left behind would keep the penguin out for good
+                                    Err(e) => {
+                                        log::warn!("failed to read from player {player_id}: {e:?}");
+                                        let _ = tx.send((player_id, Event::Disconnected)).await;
+                                        break;
+                                    }
                                 };
                             }
                             let _ = connections.write().await.remove(&player_id);
 
-
                             log::info!("connection for player {player_id} {addr} dropped");
                         }
                     });
@@ -136,13 +178,60 @@ impl Distributed {
     pub async fn push(&mut self, player_id: meta::PlayerId, xt: XTPacket) -> Result<()> {
         let mut connections = self.connections.write().await;
         match connections.get_mut(&player_id) {
-            Some(writer) => match writer.write(xt).await {
+            Some(Connection { writer, .. }) => match writer.write(xt).await {
                 Ok(()) => Ok(()),
                 Err(e) => Err(e).context("failed to send to player {player_id}"),
             },
             None => anyhow::bail!("illegal player id"),
         }
     }
+
+    /// Close the connection of `player_id`, everything pushed before is flushed already
+    pub async fn disconnect(&mut self, player_id: meta::PlayerId) {
+        match self.connections.write().await.remove(&player_id) {
+            Some(Connection { cancel, .. }) => cancel.cancel(),
+            None => log::debug!("player {player_id} is gone already"),
+        }
+    }
+}
+
+/// How long a new connection gets for its handshake and login
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+type Admitted = (
+    SocketAddr,
+    meta::PlayerId,
+    line::LineConnWriter,
+    line::LineConnReader,
+);
+
+/// Handshake and log in one connection, handing it to the accept loop once it did
+async fn admit<P: Protocol>(
+    stream: TcpStream,
+    addr: SocketAddr,
+    transport: Transport,
+    persistence: persistence::Manager,
+    keys: KeyStore,
+    policy: PolicyConfig,
+    admitted_tx: mpsc::Sender<Admitted>,
+) {
+    let login = async {
+        let (writer, reader) = transport
+            .establish(stream)
+            .await
+            .context("failed to establish connection")?;
+        authgate::gate::<P>(writer, reader, &persistence, &keys, &policy).await
+    };
+    match tokio::time::timeout(LOGIN_TIMEOUT, login).await {
+        Err(_) => log::warn!("{addr} took too long to log in, discarding"),
+        Ok(Err(e)) => log::warn!("login of {addr} failed, discarding: {e:#}"),
+        Ok(Ok((AuthResult::Unauthenticated, _, _))) => {
+            log::warn!("Bad auth result for {addr}, discarding")
+        }
+        Ok(Ok((AuthResult::Authenticated(player_id), writer, reader))) => {
+            let _ = admitted_tx.send((addr, player_id, writer, reader)).await;
+        }
+    }
 }
 
 impl Drop for Distributed {
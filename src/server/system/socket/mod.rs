@@ -1,75 +1,120 @@
 mod authgate;
 mod dist;
 
-pub mod as2 {
-    use std::net::SocketAddr;
+use std::{marker::PhantomData, sync::Arc};
 
-    use crate::{
-        pkt,
-        server::{
-            state,
-            system::{socket::dist, EventReceiver, EventSender},
-        },
-    };
+use anyhow::{Context, Result};
+use async_trait::async_trait;
 
-    use anyhow::{Context, Result};
-    use async_trait::async_trait;
-    use tokio::{net::TcpListener, sync::broadcast};
+use crate::{
+    config::Config,
+    conn::{
+        listener::{Endpoint, Listener},
+        login_key::KeyStore,
+    },
+    datamodel::catalog::Catalog,
+    persistence,
+    pkt::xt::Protocol,
+    server::{
+        state,
+        system::{EventReceiver, EventSender, System},
+        Event,
+    },
+};
 
-    use crate::server::{system::System, Event};
+/// Client facing socket of a world, speaking either as2 or as3
+pub struct Socket<P: Protocol> {
+    // tcp and websocket listeners may be mixed freely
+    pub listeners: Vec<Endpoint>,
+    pub persistence: persistence::Manager,
+    pub keys: KeyStore,
+    protocol: PhantomData<P>,
+}
 
-    pub struct Socket {
-        pub address: SocketAddr,
+impl<P: Protocol> Socket<P> {
+    pub fn new(
+        listeners: Vec<Endpoint>,
+        persistence: persistence::Manager,
+        keys: KeyStore,
+    ) -> Self {
+        Self {
+            listeners,
+            persistence,
+            keys,
+            protocol: PhantomData,
+        }
     }
+}
 
-    // TODO: this should be generic, such it also works for as3
-    #[async_trait]
-    impl System for Socket {
-        async fn instantiate(
-            &self,
-            _server: state::ServerState,
-            mut event_tx: EventSender,
-            mut event_rx: EventReceiver,
-        ) -> Result<()> {
-            let socket = TcpListener::bind(&self.address)
-                .await
-                .context("failed to bind for socket")?;
-
-            log::info!("server listening on {}", &self.address);
-            let mut dist = dist::Distributed::new(socket).await;
+#[async_trait]
+impl<P: Protocol> System for Socket<P> {
+    async fn instantiate(
+        &self,
+        config: Arc<Config>,
+        _catalog: Arc<Catalog>,
+        server: state::ServerState,
+        mut event_tx: EventSender,
+        mut event_rx: EventReceiver,
+    ) -> Result<()> {
+        let listeners = Listener::bind_all(&self.listeners)
+            .await
+            .context("failed to bind for socket")?;
 
-            tokio::spawn(async move {
-                loop {
-                    tokio::select! {
-                        event = event_rx.poll() => match event{
-                            None => break,
-                            Some(Event::PacketSent(player_id, meta)) => {
-                                let xt: pkt::xt::XTPacket = (pkt::xt::as2::server::Packet(meta)).into();
-                                dist.push(player_id, xt).await.unwrap();
+        log::info!("world socket speaks {}", P::NAME);
+        let mut dist = dist::Distributed::new::<P>(
+            listeners,
+            self.persistence.clone(),
+            self.keys.clone(),
+            config.policy(),
+        )
+        .await;
 
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    event = event_rx.poll() => match event{
+                        None => break,
+                        Some(Event::PacketSent(player_id, meta)) => {
+                            // the player may have been kicked in the meantime
+                            if let Err(e) = dist.push(player_id, P::encode(meta)).await {
+                                log::debug!("dropping packet for {player_id}: {e:#}");
                             }
-                            _ => {}
-                        },
-                        (player_id, event) = dist.poll() => match event{
-                          dist::Event::Connected => {
-                              event_tx.push(Event::PlayerConnected(player_id)).await;
-                          },
-                          dist::Event::Disconnected => event_tx.push(Event::PlayerDisconnected(player_id)).await,
-                          dist::Event::Packet(xt) => {
-                              let hack_clone = xt.clone();
-                              let as2: pkt::xt::as2::client::Packet = match xt.try_into(){
-                                  Err(e) => todo!("bad as2 from client: {e} {:?}",hack_clone),
-                                  Ok(as2) => as2,
-                              };
-                              log::debug!("received from {player_id} {as2:?} :  {hack_clone:?}");
-                              event_tx.push(Event::PacketReceived(player_id, as2.0)).await;
-                          },
                         }
+                        Some(Event::PacketSentToRoom(room_id, audience, meta)) => {
+                            let recipients: Vec<_> =
+                                server.read().await.audience(room_id, audience).collect();
+                            let xt = P::encode(meta);
+                            for player_id in recipients {
+                                if let Err(e) = dist.push(player_id, xt.clone()).await {
+                                    log::debug!("dropping packet for {player_id}: {e:#}");
+                                }
+                            }
+                        }
+                        Some(Event::DisconnectPlayer(player_id)) => dist.disconnect(player_id).await,
+                        _ => {}
+                    },
+                    (player_id, event) = dist.poll() => match event{
+                      dist::Event::Connected => {
+                          event_tx.push(Event::PlayerConnected(player_id)).await;
+                      },
+                      dist::Event::Disconnected => event_tx.push(Event::PlayerDisconnected(player_id)).await,
+                      dist::Event::Packet(xt) => {
+                          let hack_clone = xt.clone();
+                          let meta = match P::decode(xt) {
+                              Err(e) => {
+                                  log::warn!("bad {} from {player_id}: {e} {hack_clone:?}", P::NAME);
+                                  continue;
+                              }
+                              Ok(meta) => meta,
+                          };
+                          log::debug!("received from {player_id} {meta:?} :  {hack_clone:?}");
+                          event_tx.push(Event::PacketReceived(player_id, meta)).await;
+                      },
                     }
                 }
-            });
+            }
+        });
 
-            Ok(())
-        }
+        Ok(())
     }
 }